@@ -0,0 +1,43 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+};
+
+use montyformat::{MontyFormatReader, MontyFormatWriter, MoveEncoding};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 3 {
+        println!(
+            "Usage: {} <input.binpack> <output.binpack> [--legacy]",
+            args[0]
+        );
+        return;
+    }
+
+    let input = BufReader::new(File::open(&args[1]).unwrap());
+    let mut reader = if args.iter().any(|arg| arg == "--legacy") {
+        MontyFormatReader::legacy(input)
+    } else {
+        MontyFormatReader::new(input).unwrap()
+    };
+
+    let output = BufWriter::new(File::create(&args[2]).unwrap());
+    let mut writer = MontyFormatWriter::new(output).unwrap();
+
+    while let Some(game) = reader.next() {
+        let mut game = game.unwrap();
+        game.move_encoding = MoveEncoding::Compact;
+        writer.write(&game).unwrap();
+        reader.recycle(game);
+    }
+
+    let games = writer.games_written();
+    writer.finish().unwrap();
+
+    let before = std::fs::metadata(&args[1]).unwrap().len();
+    let after = std::fs::metadata(&args[2]).unwrap().len();
+
+    println!("Rewrote {games} games: {before} -> {after} bytes");
+}
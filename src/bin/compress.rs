@@ -0,0 +1,62 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+};
+
+use montyformat::{Deserialise, GameReader, GameWriter, MontyFormat, MontyValueFormat, Serialise};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 4 {
+        println!(
+            "Usage: {} <policy|value> <input.binpack> <output.binpack> [--legacy] [--decompress]",
+            args[0]
+        );
+        return;
+    }
+
+    let legacy = args.iter().any(|arg| arg == "--legacy");
+    let decompress = args.iter().any(|arg| arg == "--decompress");
+
+    let games = match args[1].as_str() {
+        "policy" => rewrite::<MontyFormat>(&args[2], &args[3], legacy, decompress),
+        "value" => rewrite::<MontyValueFormat>(&args[2], &args[3], legacy, decompress),
+        kind => {
+            println!("Unknown format kind: {kind}");
+            return;
+        }
+    };
+
+    let before = std::fs::metadata(&args[2]).unwrap().len();
+    let after = std::fs::metadata(&args[3]).unwrap().len();
+
+    println!("Rewrote {games} games: {before} -> {after} bytes");
+}
+
+fn rewrite<T: Deserialise + Serialise>(
+    input: &str,
+    output: &str,
+    legacy: bool,
+    decompress: bool,
+) -> u64 {
+    let input = BufReader::new(File::open(input).unwrap());
+    let mut reader = GameReader::<_, T>::open(input, legacy).unwrap();
+
+    let output = BufWriter::new(File::create(output).unwrap());
+    let mut writer = if decompress {
+        GameWriter::new(output).unwrap()
+    } else {
+        GameWriter::compressed(output).unwrap()
+    };
+
+    while let Some(game) = reader.next() {
+        let game = game.unwrap();
+        writer.write(&game).unwrap();
+        reader.recycle(game);
+    }
+
+    let games = writer.games_written();
+    writer.finish().unwrap();
+    games
+}
@@ -0,0 +1,45 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+};
+
+use montyformat::{MontyFormatWriter, PgnReader};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 3 {
+        println!(
+            "Usage: {} <output.binpack> <input.pgn>... [--require-scores]",
+            args[0]
+        );
+        return;
+    }
+
+    let require_scores = args.iter().any(|arg| arg == "--require-scores");
+
+    let output = BufWriter::new(File::create(&args[1]).unwrap());
+    let mut writer = MontyFormatWriter::new(output).unwrap();
+
+    let mut skipped = 0;
+
+    for path in args[2..].iter().filter(|arg| *arg != "--require-scores") {
+        let reader = PgnReader::new(BufReader::new(File::open(path).unwrap()));
+
+        for game in reader {
+            match game {
+                Ok(game) if require_scores && game.unscored > 0 => skipped += 1,
+                Ok(game) => writer.write(&game.game).unwrap(),
+                Err(err) => {
+                    println!("{path}: {err}");
+                    skipped += 1;
+                }
+            }
+        }
+    }
+
+    let written = writer.games_written();
+    writer.finish().unwrap();
+
+    println!("Wrote {written} games to {}, skipped {skipped}", args[1]);
+}
@@ -0,0 +1,33 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+};
+
+use montyformat::{MontyValueFormatWriter, TextReader};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 3 {
+        println!("Usage: {} <input.txt> <output.binpack>", args[0]);
+        return;
+    }
+
+    let reader = TextReader::new(BufReader::new(File::open(&args[1]).unwrap()));
+
+    let output = BufWriter::new(File::create(&args[2]).unwrap());
+    let mut writer = MontyValueFormatWriter::new(output).unwrap();
+
+    let mut positions = 0;
+
+    for game in reader {
+        let game = game.unwrap();
+        positions += game.moves.len();
+        writer.write(&game).unwrap();
+    }
+
+    let games = writer.games_written();
+    writer.finish().unwrap();
+
+    println!("Wrote {games} games ({positions} positions) to {}", args[2]);
+}
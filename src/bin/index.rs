@@ -0,0 +1,32 @@
+use montyformat::{GameIndex, MontyFormat, MontyValueFormat};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 3 {
+        println!("Usage: {} <policy|value> <binpack> [--legacy]", args[0]);
+        return;
+    }
+
+    let path = &args[2];
+    let legacy = args.iter().any(|arg| arg == "--legacy");
+
+    let index = match args[1].as_str() {
+        "policy" => GameIndex::build_for_file::<MontyFormat>(path, legacy),
+        "value" => GameIndex::build_for_file::<MontyValueFormat>(path, legacy),
+        kind => {
+            println!("Unknown format kind: {kind}");
+            return;
+        }
+    }
+    .unwrap();
+
+    let index_path = GameIndex::sidecar_path(path);
+    index.save(&index_path).unwrap();
+
+    println!(
+        "Indexed {} games ({} plies) into {index_path}",
+        index.len(),
+        index.total_plies()
+    );
+}
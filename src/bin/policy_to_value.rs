@@ -0,0 +1,34 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+};
+
+use montyformat::{convert_to_value, ConvertOptions, GameReader};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 3 {
+        println!(
+            "Usage: {} <input.binpack> <output.binpack> [--legacy] [--skip-plies N]",
+            args[0]
+        );
+        return;
+    }
+
+    let options = ConvertOptions {
+        skip_plies: args
+            .iter()
+            .position(|arg| arg == "--skip-plies")
+            .map_or(0, |i| args[i + 1].parse().unwrap()),
+    };
+
+    let legacy = args.iter().any(|arg| arg == "--legacy");
+
+    let reader = GameReader::open(BufReader::new(File::open(&args[1]).unwrap()), legacy).unwrap();
+    let writer = BufWriter::new(File::create(&args[2]).unwrap());
+
+    let games = convert_to_value(reader, writer, options).unwrap();
+
+    println!("Wrote {games} games to {}", args[2]);
+}
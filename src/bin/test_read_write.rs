@@ -11,8 +11,7 @@ fn main() {
 
     let mut reusable_buffer = Vec::new();
 
-    while let Ok(()) = MontyFormat::deserialise_fast_into_buffer(&mut reader, &mut reusable_buffer)
-    {
+    while MontyFormat::deserialise_fast_into_buffer(&mut reader, &mut reusable_buffer).is_ok() {
         writer.write_all(&reusable_buffer).unwrap();
     }
 }
@@ -0,0 +1,39 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+};
+
+use montyformat::{export_bullet, GameReader, MontyFormat, MontyValueFormat};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 4 {
+        println!(
+            "Usage: {} <policy|value> <binpack> <output.data> [--legacy]",
+            args[0]
+        );
+        return;
+    }
+
+    let legacy = args.iter().any(|arg| arg == "--legacy");
+
+    let reader = BufReader::new(File::open(&args[2]).unwrap());
+    let mut writer = BufWriter::new(File::create(&args[3]).unwrap());
+
+    let positions = match args[1].as_str() {
+        "policy" => GameReader::open(reader, legacy)
+            .and_then(|reader| export_bullet::<MontyFormat>(reader, &mut writer)),
+        "value" => GameReader::open(reader, legacy)
+            .and_then(|reader| export_bullet::<MontyValueFormat>(reader, &mut writer)),
+        kind => {
+            println!("Unknown format kind: {kind}");
+            return;
+        }
+    }
+    .unwrap();
+
+    writer.flush().unwrap();
+
+    println!("Wrote {positions} positions to {}", args[3]);
+}
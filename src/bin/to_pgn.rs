@@ -0,0 +1,76 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+};
+
+use montyformat::{Deserialise, GameReader, MontyFormat, MontyValueFormat, ToPgn};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 4 {
+        println!(
+            "Usage: {} <policy|value> <binpack> <output.pgn> [--legacy] [--skip N] [--count N]",
+            args[0]
+        );
+        return;
+    }
+
+    let option = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .map(|i| args[i + 1].parse::<usize>().unwrap())
+    };
+
+    let legacy = args.iter().any(|arg| arg == "--legacy");
+    let skip = option("--skip").unwrap_or(0);
+    let count = option("--count").unwrap_or(usize::MAX);
+
+    let written = match args[1].as_str() {
+        "policy" => convert::<MontyFormat>(&args[2], &args[3], legacy, skip, count),
+        "value" => convert::<MontyValueFormat>(&args[2], &args[3], legacy, skip, count),
+        kind => {
+            println!("Unknown format kind: {kind}");
+            return;
+        }
+    };
+
+    println!("Wrote {written} games to {}", args[3]);
+}
+
+fn convert<T: Deserialise + ToPgn>(
+    input: &str,
+    output: &str,
+    legacy: bool,
+    skip: usize,
+    count: usize,
+) -> usize {
+    let file = BufReader::new(File::open(input).unwrap());
+    let mut reader = GameReader::<_, T>::open(file, legacy).unwrap();
+
+    let mut writer = BufWriter::new(File::create(output).unwrap());
+    let mut written = 0;
+
+    for _ in 0..skip {
+        match reader.next() {
+            Some(game) => reader.recycle(game.unwrap()),
+            None => return 0,
+        }
+    }
+
+    while written < count {
+        let Some(game) = reader.next() else {
+            break;
+        };
+
+        let game = game.unwrap();
+        let round = (skip + written + 1).to_string();
+
+        writeln!(writer, "{}", game.to_pgn(&[("Round", &round)])).unwrap();
+        reader.recycle(game);
+        written += 1;
+    }
+
+    writer.flush().unwrap();
+    written
+}
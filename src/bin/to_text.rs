@@ -0,0 +1,40 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+};
+
+use montyformat::{export_text, GameReader, MontyFormat, MontyValueFormat};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 4 {
+        println!(
+            "Usage: {} <policy|value> <binpack> <output.txt> [--legacy] [--moves]",
+            args[0]
+        );
+        return;
+    }
+
+    let legacy = args.iter().any(|arg| arg == "--legacy");
+    let with_moves = args.iter().any(|arg| arg == "--moves");
+
+    let reader = BufReader::new(File::open(&args[2]).unwrap());
+    let mut writer = BufWriter::new(File::create(&args[3]).unwrap());
+
+    let positions = match args[1].as_str() {
+        "policy" => GameReader::open(reader, legacy)
+            .and_then(|reader| export_text::<MontyFormat>(reader, &mut writer, with_moves)),
+        "value" => GameReader::open(reader, legacy)
+            .and_then(|reader| export_text::<MontyValueFormat>(reader, &mut writer, with_moves)),
+        kind => {
+            println!("Unknown format kind: {kind}");
+            return;
+        }
+    }
+    .unwrap();
+
+    writer.flush().unwrap();
+
+    println!("Wrote {positions} positions to {}", args[3]);
+}
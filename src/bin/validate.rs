@@ -0,0 +1,45 @@
+use std::{fs::File, io::BufReader};
+
+use montyformat::{validate, GameReader, MontyFormat, MontyValueFormat};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() < 3 {
+        println!("Usage: {} <policy|value> <binpack>... [--legacy]", args[0]);
+        return;
+    }
+
+    let legacy = args.iter().any(|arg| arg == "--legacy");
+    let mut failed = false;
+
+    for path in args[2..].iter().filter(|arg| *arg != "--legacy") {
+        println!("{path}:");
+
+        let reader = BufReader::new(File::open(path).unwrap());
+
+        let report = match args[1].as_str() {
+            "policy" => GameReader::open(reader, legacy).and_then(validate::<MontyFormat>),
+            "value" => GameReader::open(reader, legacy).and_then(validate::<MontyValueFormat>),
+            kind => {
+                println!("Unknown format kind: {kind}");
+                return;
+            }
+        };
+
+        match report {
+            Ok(report) => {
+                failed |= !report.is_ok();
+                println!("{report}");
+            }
+            Err(err) => {
+                failed = true;
+                println!("{err}");
+            }
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
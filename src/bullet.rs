@@ -0,0 +1,31 @@
+use std::io::{BufRead, Write};
+
+use crate::{format::BulletChessBoard, GameReader, MontyFormatError, ScoredPositions};
+
+/// Writes every position of every game in a stream as a bulletformat
+/// `ChessBoard`. Returns the number of positions written.
+pub fn export_bullet<T: ScoredPositions>(
+    mut reader: GameReader<impl BufRead, T>,
+    writer: &mut impl Write,
+) -> Result<u64, MontyFormatError> {
+    let mut boards = Vec::new();
+    let mut positions = 0;
+
+    while let Some(game) = reader.next() {
+        let game = game?;
+
+        boards.clear();
+        game.map_scored_positions(&mut |pos, _, score, result| {
+            if let Some(board) = BulletChessBoard::new(pos, score, result) {
+                boards.extend_from_slice(&board.to_bytes());
+            }
+        });
+
+        writer.write_all(&boards)?;
+        positions += (boards.len() / BulletChessBoard::SIZE) as u64;
+
+        reader.recycle(game);
+    }
+
+    Ok(positions)
+}
@@ -1,16 +1,29 @@
 mod attacks;
 mod consts;
+mod fen;
 mod frc;
+mod legality;
 mod moves;
 mod position;
+mod san;
+mod see;
+mod state;
+mod uci;
+mod zobrist;
 
 pub const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
 pub use attacks::Attacks;
 pub use consts::{Flag, Piece, Right, Side};
+pub use fen::{CastlingNotation, FenError, FenField, FenOptions};
 pub use frc::Castling;
+pub use legality::PositionError;
 pub use moves::Move;
 pub use position::Position;
+pub use san::SanError;
+pub use see::SEE_VALUES;
+pub use state::{GameOutcome, GameState};
+pub use uci::UciMoveError;
 
 pub fn perft<const REPORT: bool>(pos: &Position, castling: &Castling, depth: u8) -> u64 {
     if depth == 1 {
@@ -24,6 +37,7 @@ pub fn perft<const REPORT: bool>(pos: &Position, castling: &Castling, depth: u8)
     pos.map_legal_moves(castling, |mov| {
         let mut new = *pos;
         new.make(mov, castling);
+        debug_assert!(new.key_is_consistent());
 
         let sub_count = perft::<false>(&new, castling, depth - 1);
 
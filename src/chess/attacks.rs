@@ -180,6 +180,7 @@ const BISHOP: [Mask; 64] = init!(|sq, 64|
 
 const RANK_SHIFT: [usize; 64] = init!(|sq, 64| sq - (sq & 7) + 1);
 
+#[allow(clippy::large_const_arrays)]
 const RANK: [[u64; 64]; 64] = init!(|sq, 64| init!(|occ, 64| {
     let file = sq & 7;
     let mask = (occ << 1) as u64;
@@ -188,6 +189,7 @@ const RANK: [[u64; 64]; 64] = init!(|sq, 64| init!(|occ, 64| {
     (EAST[file] ^ EAST[east] | WEST[file] ^ WEST[west]) << (sq - file)
 }));
 
+#[allow(clippy::large_const_arrays)]
 const FILE: [[u64; 64]; 64] = init!(|sq, 64| init!(|occ, 64| (RANK[7 - sq / 8][occ]
     .wrapping_mul(DIAG)
     & File::H)
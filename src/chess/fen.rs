@@ -0,0 +1,371 @@
+use super::{
+    consts::{Flag, Piece, Right, Side},
+    frc::Castling,
+    legality::PositionError,
+    position::Position,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FenField {
+    Board,
+    SideToMove,
+    Castling,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveNumber,
+}
+
+impl std::fmt::Display for FenField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Board => "board",
+            Self::SideToMove => "side to move",
+            Self::Castling => "castling",
+            Self::EnPassant => "en passant",
+            Self::HalfmoveClock => "halfmove clock",
+            Self::FullmoveNumber => "fullmove number",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FenError {
+    /// A FEN needs 4 to 6 space separated fields.
+    FieldCount(usize),
+    /// `index` counts characters from the start of `field`, or is the
+    /// field's length if the field ends early.
+    InvalidChar {
+        field: FenField,
+        index: usize,
+        ch: Option<char>,
+    },
+    RankCount(usize),
+    /// `rank` is numbered 1 to 8, as written in the FEN.
+    RankLength {
+        rank: u8,
+        squares: usize,
+    },
+    KingCount {
+        side: usize,
+        count: u32,
+    },
+    /// A castling right without its king on the back rank and a rook to
+    /// castle with on the right side of it.
+    CastlingMismatch(char),
+    /// An en passant square that no pawn can just have double pushed past.
+    EnPassantMismatch(String),
+    IllegalPosition(PositionError),
+    InvalidNumber {
+        field: FenField,
+        value: String,
+    },
+}
+
+impl std::fmt::Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FieldCount(count) => write!(f, "expected 4 to 6 fields, found {count}"),
+            Self::InvalidChar {
+                field,
+                index,
+                ch: Some(ch),
+            } => write!(f, "unexpected '{ch}' at index {index} of {field} field"),
+            Self::InvalidChar { field, .. } => write!(f, "{field} field ends early"),
+            Self::RankCount(count) => write!(f, "expected 8 ranks, found {count}"),
+            Self::RankLength { rank, squares } => {
+                write!(f, "rank {rank} covers {squares} squares rather than 8")
+            }
+            Self::KingCount { side, count } => write!(
+                f,
+                "{} has {count} kings rather than 1",
+                ["white", "black"][*side]
+            ),
+            Self::CastlingMismatch(ch) => {
+                write!(f, "castling right '{ch}' does not match king and rooks")
+            }
+            Self::EnPassantMismatch(square) => {
+                write!(f, "no pawn can have just double pushed past {square}")
+            }
+            Self::IllegalPosition(err) => write!(f, "illegal position, {err}"),
+            Self::InvalidNumber { field, value } => {
+                write!(f, "invalid {field} '{value}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+fn invalid_char(field: FenField, text: &str, index: usize) -> FenError {
+    FenError::InvalidChar {
+        field,
+        index,
+        ch: text.chars().nth(index),
+    }
+}
+
+/// How `Position::to_fen` writes castling rights.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CastlingNotation {
+    /// `KQkq` where the right belongs to the outermost rook on that side of
+    /// the king, and the rook's file letter otherwise. Plain FEN for
+    /// standard chess, so a Chess960 game from the standard arrangement
+    /// reads back as standard chess.
+    #[default]
+    XFen,
+    /// Always the rook's file letter, uppercase for white.
+    Shredder,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FenOptions {
+    pub castling: CastlingNotation,
+    /// Only write the en passant square if an en passant capture is legal,
+    /// rather than after every double push.
+    pub legal_en_passant_only: bool,
+}
+
+impl Position {
+    /// Strict counterpart to `parse_fen`, which checks that the FEN describes
+    /// a position that can be played from, see `validate`. Castling rights may
+    /// be given as in standard FEN, X-FEN or Shredder-FEN, and the halfmove
+    /// clock and fullmove number may be left out.
+    pub fn try_parse_fen(fen: &str) -> Result<(Self, Castling), FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+
+        if !(4..=6).contains(&fields.len()) {
+            return Err(FenError::FieldCount(fields.len()));
+        }
+
+        let bb = parse_board(fields[0])?;
+
+        for side in [Side::WHITE, Side::BLACK] {
+            let count = (bb[side] & bb[Piece::KING]).count_ones();
+
+            if count != 1 {
+                return Err(FenError::KingCount { side, count });
+            }
+        }
+
+        let stm = match fields[1] {
+            "w" => false,
+            "b" => true,
+            text => {
+                let index = usize::from(text.starts_with(['w', 'b']));
+                return Err(invalid_char(FenField::SideToMove, text, index));
+            }
+        };
+
+        let board = Self::from_raw(bb, stm, 0, 0, 0, 1);
+        let (castling, rights) = parse_castling(&board, fields[2])?;
+        let enp_sq = parse_enp(fields[3])?;
+
+        let number = |field: FenField, index: usize, default: u16| match fields.get(index) {
+            None => Ok(default),
+            Some(value) => value.parse::<u16>().map_err(|_| FenError::InvalidNumber {
+                field,
+                value: value.to_string(),
+            }),
+        };
+
+        let halfm = number(FenField::HalfmoveClock, 4, 0)?;
+        let fullm = number(FenField::FullmoveNumber, 5, 1)?;
+
+        let halfm = u8::try_from(halfm).map_err(|_| FenError::InvalidNumber {
+            field: FenField::HalfmoveClock,
+            value: fields[4].to_string(),
+        })?;
+
+        let pos = Self::from_raw(bb, stm, enp_sq, rights, halfm, fullm);
+
+        pos.validate(&castling).map_err(|err| match err {
+            PositionError::InvalidEnPassant => FenError::EnPassantMismatch(fields[3].to_string()),
+            err => FenError::IllegalPosition(err),
+        })?;
+
+        Ok((pos, castling))
+    }
+
+    /// FEN of the position, reading castling rights through `castling` so
+    /// that Chess960 positions are written correctly. With default options
+    /// the result parses back to the same position with `parse_fen`.
+    #[must_use]
+    pub fn to_fen(&self, castling: &Castling, options: FenOptions) -> String {
+        let fen = self.as_fen();
+        let mut fields: Vec<&str> = fen.split(' ').collect();
+
+        let rights = self.castling_field(castling, options.castling);
+        fields[2] = &rights;
+
+        if options.legal_en_passant_only && self.enp_sq() > 0 {
+            let mut legal = false;
+            self.map_legal_captures(castling, |mov| legal |= mov.flag() == Flag::ENP);
+
+            if !legal {
+                fields[3] = "-";
+            }
+        }
+
+        fields.join(" ")
+    }
+
+    fn castling_field(&self, castling: &Castling, notation: CastlingNotation) -> String {
+        let mut field = String::new();
+
+        for (side, ks, right, outer, base) in [
+            (0, 1, Right::WKS, 'K', b'A'),
+            (0, 0, Right::WQS, 'Q', b'A'),
+            (1, 1, Right::BKS, 'k', b'a'),
+            (1, 0, Right::BQS, 'q', b'a'),
+        ] {
+            if self.rights() & right == 0 {
+                continue;
+            }
+
+            let file = castling.rook_files()[side][ks];
+            let rooks = ((self.piece(side) & self.piece(Piece::ROOK)) >> (56 * side)) as u8;
+
+            // rooks on files strictly further from the king than this one
+            let beyond = if ks == 1 {
+                rooks & !((2u16 << file) - 1) as u8
+            } else {
+                rooks & ((1u16 << file) - 1) as u8
+            };
+
+            if notation == CastlingNotation::XFen && rooks & (1 << file) > 0 && beyond == 0 {
+                field.push(outer);
+            } else {
+                field.push((base + file) as char);
+            }
+        }
+
+        if field.is_empty() {
+            field.push('-');
+        }
+
+        field
+    }
+}
+
+fn parse_board(board: &str) -> Result<[u64; 8], FenError> {
+    let ranks: Vec<&str> = board.split('/').collect();
+
+    if ranks.len() != 8 {
+        return Err(FenError::RankCount(ranks.len()));
+    }
+
+    let mut bb = [0; 8];
+    let mut index = 0;
+
+    for (i, text) in ranks.iter().enumerate() {
+        let rank = 7 - i;
+        let mut file = 0;
+
+        for ch in text.chars() {
+            match ch {
+                '1'..='8' => file += ch as usize - '0' as usize,
+                _ => {
+                    let idx = "PNBRQKpnbrqk"
+                        .chars()
+                        .position(|pc| pc == ch)
+                        .ok_or_else(|| invalid_char(FenField::Board, board, index))?;
+
+                    if file < 8 {
+                        let bit = 1 << (8 * rank + file);
+                        bb[idx / 6] |= bit;
+                        bb[idx % 6 + 2] |= bit;
+                    }
+
+                    file += 1;
+                }
+            }
+
+            index += 1;
+        }
+
+        if file != 8 {
+            return Err(FenError::RankLength {
+                rank: rank as u8 + 1,
+                squares: file,
+            });
+        }
+
+        // the '/' after the rank
+        index += 1;
+    }
+
+    Ok(bb)
+}
+
+fn parse_castling(board: &Position, text: &str) -> Result<(Castling, u8), FenError> {
+    let mut castling = Castling::default();
+
+    if text == "-" {
+        castling.parse(board, "");
+        return Ok((castling, 0));
+    }
+
+    for (index, ch) in text.chars().enumerate() {
+        if !matches!(ch, 'K' | 'Q' | 'k' | 'q' | 'A'..='H' | 'a'..='h') {
+            return Err(invalid_char(FenField::Castling, text, index));
+        }
+    }
+
+    let rights = castling.parse(board, text);
+    let mut seen = 0;
+
+    for (index, ch) in text.chars().enumerate() {
+        let side = usize::from(ch.is_ascii_lowercase());
+        let king = board.king_sq(side);
+
+        let ks = match ch.to_ascii_uppercase() {
+            'K' => 1,
+            'Q' => 0,
+            file => usize::from(file as usize - 'A' as usize > king % 8),
+        };
+
+        let right = [[Right::WQS, Right::WKS], [Right::BQS, Right::BKS]][side][ks];
+
+        if seen & right > 0 {
+            return Err(invalid_char(FenField::Castling, text, index));
+        }
+
+        seen |= right;
+
+        let file = usize::from(castling.rook_file(side, ks));
+        let rook = 56 * side + file;
+        let rooks = board.piece(side) & board.piece(Piece::ROOK);
+
+        if king / 8 != 7 * side || rooks & (1 << rook) == 0 || (ks == 1) != (file > king % 8) {
+            return Err(FenError::CastlingMismatch(ch));
+        }
+    }
+
+    Ok((castling, rights))
+}
+
+fn parse_enp(text: &str) -> Result<u8, FenError> {
+    if text == "-" {
+        return Ok(0);
+    }
+
+    let bytes = text.as_bytes();
+
+    for (index, range) in [b'a'..=b'h', b'1'..=b'8'].iter().enumerate() {
+        if !bytes.get(index).is_some_and(|byte| range.contains(byte)) {
+            return Err(invalid_char(FenField::EnPassant, text, index));
+        }
+    }
+
+    if bytes.len() > 2 {
+        return Err(invalid_char(FenField::EnPassant, text, 2));
+    }
+
+    if !matches!(bytes[1], b'3' | b'6') {
+        return Err(FenError::EnPassantMismatch(text.to_string()));
+    }
+
+    Ok(8 * (bytes[1] - b'1') + bytes[0] - b'a')
+}
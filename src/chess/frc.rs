@@ -58,6 +58,21 @@ impl Castling {
         ret.castle_mask[pos.king_sq(0)] = 3;
         ret.castle_mask[pos.king_sq(1)] = 12;
 
+        // as in `parse_outer_castle`, only rights off the standard files
+        // make the game Chess960
+        for (side, ks, right) in [
+            (Side::WHITE, 0, Right::WQS),
+            (Side::WHITE, 1, Right::WKS),
+            (Side::BLACK, 0, Right::BQS),
+            (Side::BLACK, 1, Right::BKS),
+        ] {
+            if pos.rights() & right > 0
+                && (pos.king_sq(side) % 8 != 4 || rook_files[side][ks] != [0, 7][ks])
+            {
+                ret.chess960 = true;
+            }
+        }
+
         ret
     }
 
@@ -72,10 +87,10 @@ impl Castling {
 
         let rights = rights_str.chars().fold(0, |cr, ch| {
             cr | match ch as u8 {
-                b'Q' => Right::WQS,
-                b'K' => Right::WKS,
-                b'q' => Right::BQS,
-                b'k' => Right::BKS,
+                b'Q' => self.parse_outer_castle(pos, Side::WHITE, 0, &mut kings),
+                b'K' => self.parse_outer_castle(pos, Side::WHITE, 1, &mut kings),
+                b'q' => self.parse_outer_castle(pos, Side::BLACK, 0, &mut kings),
+                b'k' => self.parse_outer_castle(pos, Side::BLACK, 1, &mut kings),
                 b'A'..=b'H' => self.parse_castle(pos, Side::WHITE, &mut kings, ch),
                 b'a'..=b'h' => self.parse_castle(pos, Side::BLACK, &mut kings, ch),
                 _ => 0,
@@ -96,6 +111,41 @@ impl Castling {
         rights
     }
 
+    /// `KQkq` as in X-FEN: the right refers to the outermost rook on that
+    /// side of the king, which is only Chess960 if king or rook is not on
+    /// its standard file.
+    fn parse_outer_castle(
+        &mut self,
+        pos: &Position,
+        side: usize,
+        ks: usize,
+        kings: &mut [usize; 2],
+    ) -> u8 {
+        let king_bb = pos.piece(side) & pos.piece(Piece::KING);
+        let king = king_bb.trailing_zeros() as u8 & 7;
+        let rooks = ((pos.piece(side) & pos.piece(Piece::ROOK)) >> (56 * side)) as u8;
+
+        let rook = if ks == 1 {
+            let outside = rooks & !((2u16 << king) - 1) as u8;
+            (outside > 0).then(|| 7 - outside.leading_zeros() as u8)
+        } else {
+            let outside = rooks & ((1u16 << king) - 1) as u8;
+            (outside > 0).then(|| outside.trailing_zeros() as u8)
+        };
+
+        // without a rook or king to go by, fall back to the standard files
+        if let Some(rook) = rook.filter(|_| king_bb > 0) {
+            if king != 4 || rook != [0, 7][ks] {
+                self.chess960 = true;
+            }
+
+            kings[side] = usize::from(king);
+            self.rook_files[side][ks] = rook;
+        }
+
+        [[Right::WQS, Right::WKS], [Right::BQS, Right::BKS]][side][ks]
+    }
+
     fn parse_castle(
         &mut self,
         pos: &Position,
@@ -115,3 +165,29 @@ impl Castling {
         [[Right::WQS, Right::WKS], [Right::BQS, Right::BKS]][side][i]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_raw_agrees_with_parse() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqnbkr/pppppppp/8/8/8/8/PPPPPPPP/RNBQNK1R w KQkq - 0 1",
+            "rnbqnbkr/pppppppp/8/8/8/8/PPPPPPPP/RNBQNK1R w HAha - 0 1",
+            "bnrbkrqn/pppppppp/8/8/8/8/PPPPPPPP/BNRBKRQN w KQkq - 0 1",
+            "1r2k1r1/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+            "4k3/8/8/8/8/8/8/R3K2R w K - 0 1",
+            "r3k2r/8/8/8/8/8/8/5K2 b kq - 0 1",
+            "4k3/8/8/8/8/8/8/2K5 w - - 0 1",
+        ] {
+            let mut parsed = Castling::default();
+            let pos = Position::parse_fen(fen, &mut parsed);
+            let raw = Castling::from_raw(&pos, parsed.rook_files());
+
+            assert_eq!(raw.is_chess960(), parsed.is_chess960(), "{fen}");
+            assert_eq!(raw.rook_files(), parsed.rook_files(), "{fen}");
+        }
+    }
+}
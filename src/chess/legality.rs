@@ -0,0 +1,129 @@
+use super::{
+    consts::{Piece, Right, Side},
+    frc::Castling,
+    position::Position,
+};
+
+/// Why a position cannot arise in a game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PositionError {
+    /// A square holds more than one piece, or a piece has no colour.
+    OverlappingPieces,
+    KingCount {
+        side: usize,
+        count: u32,
+    },
+    PawnOnBackRank,
+    OpponentInCheck,
+    /// The en passant square is not behind a pawn that could just have
+    /// double pushed past it.
+    InvalidEnPassant,
+    /// A castling right, one of `Right`, without the king on its back rank
+    /// and a rook on the right side of it to castle with.
+    CastlingMismatch(u8),
+}
+
+impl PositionError {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::OverlappingPieces => "pieces overlap",
+            Self::KingCount { .. } => "side does not have exactly one king",
+            Self::PawnOnBackRank => "pawn on back rank",
+            Self::OpponentInCheck => "side not to move is in check",
+            Self::InvalidEnPassant => "en passant square without a double pushed pawn",
+            Self::CastlingMismatch(_) => "castling rights do not match king and rooks",
+        }
+    }
+}
+
+impl std::fmt::Display for PositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.reason())
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+impl Position {
+    /// Checks that the position could arise in a game of chess played under
+    /// `castling`, as far as can be told without the moves leading to it.
+    pub fn validate(&self, castling: &Castling) -> Result<(), PositionError> {
+        let bb = self.bbs();
+        let pieces = bb[Piece::PAWN..=Piece::KING]
+            .iter()
+            .fold(0, |acc, bb| acc | bb);
+        let overlaps = (Piece::PAWN..=Piece::KING)
+            .any(|pc| (pc + 1..=Piece::KING).any(|other| bb[pc] & bb[other] > 0));
+
+        if overlaps || bb[Side::WHITE] & bb[Side::BLACK] > 0 || pieces != self.occ() {
+            return Err(PositionError::OverlappingPieces);
+        }
+
+        for side in [Side::WHITE, Side::BLACK] {
+            let count = (bb[side] & bb[Piece::KING]).count_ones();
+
+            if count != 1 {
+                return Err(PositionError::KingCount { side, count });
+            }
+        }
+
+        if bb[Piece::PAWN] & 0xFF00_0000_0000_00FF > 0 {
+            return Err(PositionError::PawnOnBackRank);
+        }
+
+        let stm = self.stm();
+
+        if self.is_square_attacked(self.king_sq(stm ^ 1), stm ^ 1, self.occ()) {
+            return Err(PositionError::OpponentInCheck);
+        }
+
+        if self.enp_sq() > 0 && !self.valid_enp_sq() {
+            return Err(PositionError::InvalidEnPassant);
+        }
+
+        for (side, ks, right) in [
+            (Side::WHITE, 1, Right::WKS),
+            (Side::WHITE, 0, Right::WQS),
+            (Side::BLACK, 1, Right::BKS),
+            (Side::BLACK, 0, Right::BQS),
+        ] {
+            if self.rights() & right == 0 {
+                continue;
+            }
+
+            let king = self.king_sq(side);
+            let file = usize::from(castling.rook_file(side, ks));
+            let rook = 1 << (56 * side + file);
+
+            if king / 8 != 7 * side
+                || bb[side] & bb[Piece::ROOK] & rook == 0
+                || (ks == 1) != (file > king % 8)
+            {
+                return Err(PositionError::CastlingMismatch(right));
+            }
+        }
+
+        if self.rights() > 15 {
+            return Err(PositionError::CastlingMismatch(self.rights() & !15));
+        }
+
+        Ok(())
+    }
+
+    fn valid_enp_sq(&self) -> bool {
+        let sq = self.enp_sq();
+
+        // the pawn that just moved belongs to the side not to move
+        let moved = self.stm() ^ 1;
+
+        if sq >= 64 || sq / 8 != [2, 5][moved] {
+            return false;
+        }
+
+        let pawn = sq ^ 8;
+        let from = 2 * sq - pawn;
+        let pawns = self.piece(moved) & self.piece(Piece::PAWN);
+
+        pawns & (1 << pawn) > 0 && self.occ() & ((1 << sq) | (1 << from)) == 0
+    }
+}
@@ -5,6 +5,8 @@ use super::{
     consts::*,
     frc::Castling,
     moves::{serialise, Move},
+    san::square_name,
+    zobrist::ZOBRIST,
 };
 
 #[derive(Copy, Clone, Default, PartialEq, Eq)]
@@ -15,6 +17,7 @@ pub struct Position {
     rights: u8,
     halfm: u8,
     fullm: u16,
+    key: u64,
 }
 
 impl Position {
@@ -26,14 +29,18 @@ impl Position {
         halfm: u8,
         fullm: u16,
     ) -> Self {
-        Self {
+        let mut ret = Self {
             bb,
             stm,
             enp_sq,
             rights,
             halfm,
             fullm,
-        }
+            key: 0,
+        };
+
+        ret.key = ret.key_from_scratch();
+        ret
     }
 
     #[must_use]
@@ -70,6 +77,63 @@ impl Position {
         self.fullm
     }
 
+    /// Zobrist hash of the pieces, side to move, castling rights and en
+    /// passant square. Each castling right is keyed by the square of its
+    /// rook, and the en passant square only counts if a pawn could capture
+    /// onto it, so positions that play the same hash the same.
+    #[must_use]
+    pub fn hash(&self, castling: &Castling) -> u64 {
+        let mut hash = self.key;
+        let mut rights = self.rights;
+
+        // bits of `rights` run BKS, BQS, WKS, WQS from the lowest
+        while rights > 0 {
+            let bit = rights.trailing_zeros() as usize;
+            rights &= rights - 1;
+
+            let side = usize::from(bit < 2);
+            let ks = usize::from(bit & 1 == 0);
+            let rook = 56 * side + usize::from(castling.rook_file(side, ks));
+            hash ^= ZOBRIST.castling[rook];
+        }
+
+        hash
+    }
+
+    /// Whether the key kept up to date by `make` matches the pieces. Only
+    /// meaningful after legal moves, as `make` does not check what it is
+    /// given.
+    pub(super) fn key_is_consistent(&self) -> bool {
+        self.key == self.key_from_scratch()
+    }
+
+    fn key_from_scratch(&self) -> u64 {
+        let mut key = self.enp_key();
+
+        if self.stm {
+            key ^= ZOBRIST.stm;
+        }
+
+        for side in [Side::WHITE, Side::BLACK] {
+            for pc in Piece::PAWN..=Piece::KING {
+                bitloop!(|self.bb[side] & self.bb[pc], sq| key ^= ZOBRIST.pieces[side][pc][usize::from(sq)]);
+            }
+        }
+
+        key
+    }
+
+    fn enp_key(&self) -> u64 {
+        let enp_sq = usize::from(self.enp_sq);
+        let capturers = self.bb[Piece::PAWN] & self.boys();
+
+        if enp_sq > 0 && Attacks::pawn(enp_sq, self.stm() ^ 1) & capturers > 0 {
+            ZOBRIST.enp[enp_sq & 7]
+        } else {
+            0
+        }
+    }
+
     #[must_use]
     pub fn occ(&self) -> u64 {
         self.bb[Side::WHITE] | self.bb[Side::BLACK]
@@ -167,6 +231,7 @@ impl Position {
         let bit = 1 << sq;
         self.bb[piece] ^= bit;
         self.bb[side] ^= bit;
+        self.key ^= ZOBRIST.pieces[side][piece][usize::from(sq)];
     }
 
     pub fn make(&mut self, mov: Move, castling: &Castling) {
@@ -181,6 +246,7 @@ impl Position {
         };
 
         // updating state
+        self.key ^= self.enp_key() ^ ZOBRIST.stm;
         self.stm = !self.stm;
         self.enp_sq = 0;
         self.rights &= castling.mask(usize::from(mov.to())) & castling.mask(usize::from(mov.src()));
@@ -219,10 +285,14 @@ impl Position {
             }
             _ => {}
         }
+
+        self.key ^= self.enp_key();
     }
 
     // CREATE POSITION
 
+    /// Parses a FEN from a trusted source, panicking or guessing on bad
+    /// input. See `try_parse_fen` for anything else.
     #[must_use]
     pub fn parse_fen(fen: &str, castling: &mut Castling) -> Self {
         let mut pos = Self::default();
@@ -267,6 +337,8 @@ impl Position {
 
         pos.fullm = vec[5].parse::<u16>().unwrap_or(1);
 
+        pos.key = pos.key_from_scratch();
+
         pos
     }
 
@@ -559,6 +631,8 @@ impl Position {
         });
     }
 
+    /// FEN with castling rights as `KQkq`, which is only exact for standard
+    /// chess. See `to_fen` for Chess960.
     pub fn as_fen(&self) -> String {
         const PIECES: [char; 12] = ['P', 'N', 'B', 'R', 'Q', 'K', 'p', 'n', 'b', 'r', 'q', 'k'];
         let mut fen = String::new();
@@ -597,15 +671,27 @@ impl Position {
         if self.rights == 0 {
             fen.push('-');
         } else {
-            let mut r = self.rights;
-            while r > 0 {
-                let q = r.trailing_zeros();
-                r &= r - 1;
-                fen.push(['k', 'q', 'K', 'Q'][q as usize]);
+            for (right, ch) in [
+                (Right::WKS, 'K'),
+                (Right::WQS, 'Q'),
+                (Right::BKS, 'k'),
+                (Right::BQS, 'q'),
+            ] {
+                if self.rights & right > 0 {
+                    fen.push(ch);
+                }
             }
         }
 
-        fen.push_str(&format!(" - {} {}", self.halfm(), self.fullm()));
+        fen.push(' ');
+
+        if self.enp_sq > 0 {
+            fen.push_str(&square_name(u16::from(self.enp_sq)));
+        } else {
+            fen.push('-');
+        }
+
+        fen.push_str(&format!(" {} {}", self.halfm(), self.fullm()));
 
         fen
     }
@@ -0,0 +1,271 @@
+use super::{
+    consts::{Flag, Piece},
+    frc::Castling,
+    moves::Move,
+    position::Position,
+};
+
+const PIECE_CHARS: [char; 8] = [' ', ' ', 'P', 'N', 'B', 'R', 'Q', 'K'];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SanError {
+    /// The text is not shaped like a move.
+    Invalid,
+    /// No legal move matches.
+    Illegal,
+    /// More than one legal move matches.
+    Ambiguous,
+}
+
+impl std::fmt::Display for SanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid => write!(f, "not a valid move"),
+            Self::Illegal => write!(f, "no legal move matches"),
+            Self::Ambiguous => write!(f, "more than one legal move matches"),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+struct ParsedSan {
+    piece: usize,
+    file: Option<u16>,
+    rank: Option<u16>,
+    to: u16,
+    promo: Option<usize>,
+}
+
+impl ParsedSan {
+    fn parse(san: &str) -> Option<Self> {
+        let mut chars: Vec<char> = san.chars().filter(|ch| !"()".contains(*ch)).collect();
+
+        let piece = match chars.first()? {
+            &ch if "NBRQK".contains(ch) => {
+                chars.remove(0);
+                PIECE_CHARS.iter().position(|&pc| pc == ch)?
+            }
+            _ => Piece::PAWN,
+        };
+
+        let mut promo = None;
+        if piece == Piece::PAWN {
+            if let Some(pc) = chars
+                .last()
+                .and_then(|ch| "nbrq".find(ch.to_ascii_lowercase()))
+            {
+                promo = Some(pc + Piece::KNIGHT);
+                chars.pop();
+
+                if chars.last().is_some_and(|ch| "=/".contains(*ch)) {
+                    chars.pop();
+                }
+            }
+        }
+
+        let rank = chars
+            .pop()?
+            .to_digit(10)
+            .filter(|rank| (1..=8).contains(rank))? as u16
+            - 1;
+        let file = file_index(chars.pop()?)?;
+
+        if chars.last().is_some_and(|ch| "x:-".contains(*ch)) {
+            chars.pop();
+        }
+
+        let (from_file, from_rank) = match chars[..] {
+            [] => (None, None),
+            [ch] if file_index(ch).is_some() => (file_index(ch), None),
+            [ch] => (
+                None,
+                Some(ch.to_digit(10).filter(|r| (1..=8).contains(r))? as u16 - 1),
+            ),
+            [f, r] => (
+                Some(file_index(f)?),
+                Some(r.to_digit(10).filter(|r| (1..=8).contains(r))? as u16 - 1),
+            ),
+            _ => return None,
+        };
+
+        Some(Self {
+            piece,
+            file: from_file,
+            rank: from_rank,
+            to: 8 * rank + file,
+            promo,
+        })
+    }
+}
+
+fn file_index(ch: char) -> Option<u16> {
+    ('a'..='h').contains(&ch).then(|| ch as u16 - 'a' as u16)
+}
+
+pub(crate) fn square_name(sq: u16) -> String {
+    format!("{}{}", (b'a' + (sq & 7) as u8) as char, sq / 8 + 1)
+}
+
+impl Position {
+    /// Standard algebraic notation for a legal move, including check and
+    /// mate suffixes.
+    #[must_use]
+    pub fn san(&self, mov: Move, castling: &Castling) -> String {
+        let mut san = self.san_without_suffix(mov, castling);
+
+        let mut next = *self;
+        next.make(mov, castling);
+
+        if next.in_check() {
+            let mut has_moves = false;
+            next.map_legal_moves(castling, |_| has_moves = true);
+            san.push(if has_moves { '+' } else { '#' });
+        }
+
+        san
+    }
+
+    /// Finds the legal move written as `san`. Check, mate and annotation
+    /// suffixes are ignored, and common variants are accepted: `0-0` for
+    /// castling, `e8Q` or `e8(Q)` for promotions, an `e.p.` suffix, missing
+    /// or redundant capture marks and over-disambiguated moves.
+    pub fn parse_san(&self, san: &str, castling: &Castling) -> Result<Move, SanError> {
+        let san = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let san = san.strip_suffix("e.p.").unwrap_or(san).trim_end();
+
+        if san.is_empty() {
+            return Err(SanError::Invalid);
+        }
+
+        if san.chars().all(|ch| "O0-".contains(ch)) {
+            let flag = match san.chars().filter(|&ch| ch != '-').count() {
+                2 => Flag::KS,
+                3 => Flag::QS,
+                _ => return Err(SanError::Invalid),
+            };
+
+            return self.find_unique(castling, |mov| mov.flag() == flag);
+        }
+
+        let parsed = ParsedSan::parse(san).ok_or(SanError::Invalid)?;
+        let ksq = self.king_sq(self.stm());
+
+        // Chess960 castling is sometimes written as the king capturing its
+        // own rook.
+        if parsed.piece == Piece::KING
+            && self.boys() & self.piece(Piece::ROOK) & (1 << parsed.to) > 0
+        {
+            let flag = if usize::from(parsed.to) > ksq {
+                Flag::KS
+            } else {
+                Flag::QS
+            };
+
+            return self.find_unique(castling, |mov| mov.flag() == flag);
+        }
+
+        self.find_unique(castling, |mov| {
+            if [Flag::KS, Flag::QS].contains(&mov.flag())
+                || mov.to() != parsed.to
+                || self.get_pc(1 << mov.src()) != parsed.piece
+                || parsed.file.is_some_and(|file| mov.src() & 7 != file)
+                || parsed.rank.is_some_and(|rank| mov.src() / 8 != rank)
+            {
+                return false;
+            }
+
+            if parsed.piece == Piece::PAWN && parsed.file.is_none() && mov.src() & 7 != mov.to() & 7
+            {
+                return false;
+            }
+
+            match parsed.promo {
+                Some(pc) => mov.is_promo() && mov.promo_pc() == pc,
+                None => !mov.is_promo(),
+            }
+        })
+    }
+
+    fn find_unique(
+        &self,
+        castling: &Castling,
+        mut matches: impl FnMut(Move) -> bool,
+    ) -> Result<Move, SanError> {
+        let mut found = Err(SanError::Illegal);
+
+        self.map_legal_moves(castling, |mov| {
+            if matches(mov) {
+                found = match found {
+                    Err(SanError::Illegal) => Ok(mov),
+                    _ => Err(SanError::Ambiguous),
+                };
+            }
+        });
+
+        found
+    }
+
+    fn san_without_suffix(&self, mov: Move, castling: &Castling) -> String {
+        match mov.flag() {
+            Flag::KS => return "O-O".to_string(),
+            Flag::QS => return "O-O-O".to_string(),
+            _ => {}
+        }
+
+        let pc = self.get_pc(1 << mov.src());
+        let mut san = String::new();
+
+        if pc == Piece::PAWN {
+            if mov.is_capture() {
+                san.push((b'a' + (mov.src() & 7) as u8) as char);
+                san.push('x');
+            }
+
+            san += &square_name(mov.to());
+
+            if mov.is_promo() {
+                san.push('=');
+                san.push(PIECE_CHARS[mov.promo_pc()]);
+            }
+
+            return san;
+        }
+
+        san.push(PIECE_CHARS[pc]);
+
+        if pc != Piece::KING {
+            let mut others = Vec::new();
+            self.map_legal_moves(castling, |other| {
+                if other.to() == mov.to()
+                    && other.src() != mov.src()
+                    && ![Flag::KS, Flag::QS].contains(&other.flag())
+                    && self.get_pc(1 << other.src()) == pc
+                {
+                    others.push(other.src());
+                }
+            });
+
+            if !others.is_empty() {
+                let file = mov.src() & 7;
+                let rank = mov.src() / 8;
+
+                if others.iter().all(|sq| sq & 7 != file) {
+                    san.push((b'a' + file as u8) as char);
+                } else if others.iter().all(|sq| sq / 8 != rank) {
+                    san.push((b'1' + rank as u8) as char);
+                } else {
+                    san += &square_name(mov.src());
+                }
+            }
+        }
+
+        if mov.is_capture() {
+            san.push('x');
+        }
+
+        san += &square_name(mov.to());
+
+        san
+    }
+}
@@ -0,0 +1,281 @@
+use super::{
+    attacks::Attacks,
+    consts::{Flag, Piece, Side, LINE_THROUGH},
+    moves::Move,
+    position::Position,
+};
+
+/// Piece values used by static exchange evaluation, indexed like
+/// `Position`'s bitboards.
+pub const SEE_VALUES: [i32; 8] = [0, 0, 100, 450, 450, 650, 1250, 0];
+
+impl Position {
+    /// Whether `mov` wins at least `threshold` material by static exchange
+    /// evaluation, see `see_value`.
+    #[must_use]
+    pub fn see(&self, mov: Move, threshold: i32) -> bool {
+        self.see_value(mov) >= threshold
+    }
+
+    /// Material won by the side to move by playing `mov`, in `SEE_VALUES`,
+    /// if both sides then recapture on its destination with their least
+    /// valuable piece for as long as it pays. Pawns recapturing onto the
+    /// last rank promote to a queen, pinned pieces only capture along their
+    /// pin, and the king only captures onto an undefended square. Castling
+    /// is worth nothing.
+    #[must_use]
+    pub fn see_value(&self, mov: Move) -> i32 {
+        if [Flag::KS, Flag::QS].contains(&mov.flag()) {
+            return 0;
+        }
+
+        let bb = self.bbs();
+        let sq = usize::from(mov.to());
+        let from = usize::from(mov.src());
+        let promo_bonus = SEE_VALUES[Piece::QUEEN] - SEE_VALUES[Piece::PAWN];
+
+        let mut occ = (self.occ() ^ (1 << from)) | (1 << sq);
+        let mut gain = [0; 32];
+        let mut on_sq = self.get_pc(1 << from);
+
+        gain[0] = if mov.flag() == Flag::ENP {
+            occ ^= 1 << (sq ^ 8);
+            SEE_VALUES[Piece::PAWN]
+        } else if mov.is_capture() {
+            SEE_VALUES[self.get_pc(1 << sq)]
+        } else {
+            0
+        };
+
+        if mov.is_promo() {
+            on_sq = mov.promo_pc();
+            gain[0] += SEE_VALUES[on_sq] - SEE_VALUES[Piece::PAWN];
+        }
+
+        let bishops = bb[Piece::BISHOP] | bb[Piece::QUEEN];
+        let rooks = bb[Piece::ROOK] | bb[Piece::QUEEN];
+
+        let mut attackers = (self.attackers_to_square(sq, Side::WHITE, occ)
+            | self.attackers_to_square(sq, Side::BLACK, occ))
+            & occ;
+
+        let mut side = self.stm() ^ 1;
+        let mut depth = 0;
+
+        loop {
+            let mut ours = attackers & bb[side];
+
+            let pinnable = ours & !bb[Piece::KING];
+            crate::bitloop!(|pinnable, attacker| {
+                if !self.pin_allows(side, usize::from(attacker), sq, occ) {
+                    ours ^= 1 << attacker;
+                }
+            });
+
+            let Some(pc) = (Piece::PAWN..=Piece::KING).find(|&pc| ours & bb[pc] > 0) else {
+                break;
+            };
+
+            if pc == Piece::KING && attackers & bb[side ^ 1] > 0 {
+                break;
+            }
+
+            let promotes = pc == Piece::PAWN && !(8..56).contains(&sq);
+
+            depth += 1;
+            gain[depth] = SEE_VALUES[on_sq] - gain[depth - 1];
+
+            if promotes {
+                gain[depth] += promo_bonus;
+            }
+
+            on_sq = if promotes { Piece::QUEEN } else { pc };
+            occ ^= 1 << (ours & bb[pc]).trailing_zeros();
+
+            // x-rays through the piece that just captured
+            if [Piece::PAWN, Piece::BISHOP, Piece::QUEEN].contains(&pc) {
+                attackers |= Attacks::bishop(sq, occ) & bishops;
+            }
+
+            if [Piece::ROOK, Piece::QUEEN].contains(&pc) {
+                attackers |= Attacks::rook(sq, occ) & rooks;
+            }
+
+            attackers &= occ;
+            side ^= 1;
+        }
+
+        while depth > 0 {
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+            depth -= 1;
+        }
+
+        gain[0]
+    }
+
+    /// Whether the piece of `side` on `from` can capture on `sq` without
+    /// exposing its king to a slider still in `occ`.
+    fn pin_allows(&self, side: usize, from: usize, sq: usize, occ: u64) -> bool {
+        let king = self.king_sq(side);
+        let line = LINE_THROUGH[king][from];
+
+        if line & (1 << sq) > 0 {
+            return true;
+        }
+
+        // the piece on `sq` is about to be captured, so cannot be the pinner
+        let opps = self.piece(side ^ 1) & occ & !(1 << sq);
+        let rooks = opps & (self.piece(Piece::ROOK) | self.piece(Piece::QUEEN));
+        let bishops = opps & (self.piece(Piece::BISHOP) | self.piece(Piece::QUEEN));
+
+        let without = occ ^ (1 << from);
+        let pinners =
+            (Attacks::rook(king, without) & rooks) | (Attacks::bishop(king, without) & bishops);
+
+        pinners & line == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const P: i32 = SEE_VALUES[Piece::PAWN];
+    const N: i32 = SEE_VALUES[Piece::KNIGHT];
+    const B: i32 = SEE_VALUES[Piece::BISHOP];
+    const R: i32 = SEE_VALUES[Piece::ROOK];
+    const Q: i32 = SEE_VALUES[Piece::QUEEN];
+
+    // (fen, move, material won)
+    const SUITE: [(&str, &str, i32); 34] = [
+        (
+            "6k1/1pp4p/p1pb4/6q1/3P1pRr/2P4P/PP1Br1P1/5RKN w - - 0 1",
+            "f1f4",
+            P - R + B,
+        ),
+        (
+            "5rk1/1pp2q1p/p1pb4/8/3P1NP1/2P5/1P1BQ1P1/5RK1 b - - 0 1",
+            "d6f4",
+            N - B,
+        ),
+        (
+            "4R3/2r3p1/5bk1/1p1r3p/p2PR1P1/P1BK1P2/1P6/8 b - - 0 1",
+            "h5g4",
+            0,
+        ),
+        (
+            "4R3/2r3p1/5bk1/1p1r1p1p/p2PR1P1/P1BK1P2/1P6/8 b - - 0 1",
+            "h5g4",
+            0,
+        ),
+        (
+            "4r1k1/5pp1/nbp4p/1p2p2q/1P2P1b1/1BP2N1P/1B2QPPK/3R4 b - - 0 1",
+            "g4f3",
+            N - B,
+        ),
+        (
+            "2r1r1k1/pp1bppbp/3p1np1/q3P3/2P2P2/1P2B3/P1N1B1PP/2RQ1RK1 b - - 0 1",
+            "d6e5",
+            P,
+        ),
+        (
+            "7r/5qpk/p1Qp1b1p/3r3n/BB3p2/5p2/P1P2P2/4RK1R w - - 0 1",
+            "e1e8",
+            0,
+        ),
+        (
+            "6rr/6pk/p1Qp1b1p/2n5/1B3p2/5p2/P1P2P2/4RK1R w - - 0 1",
+            "e1e8",
+            -R,
+        ),
+        (
+            "7r/5qpk/2Qp1b1p/1N1r3n/BB3p2/5p2/P1P2P2/4RK1R w - - 0 1",
+            "e1e8",
+            -R,
+        ),
+        ("6RR/4bP2/8/8/5r2/3K4/5p2/4k3 w - - 0 1", "f7f8q", B - P),
+        ("6RR/4bP2/8/8/5r2/3K4/5p2/4k3 w - - 0 1", "f7f8n", N - P),
+        ("7R/5P2/8/8/6r1/3K4/5p2/4k3 w - - 0 1", "f7f8q", Q - P),
+        ("7R/5P2/8/8/6r1/3K4/5p2/4k3 w - - 0 1", "f7f8b", B - P),
+        ("7R/4bP2/8/8/1q6/3K4/5p2/4k3 w - - 0 1", "f7f8r", -P),
+        (
+            "8/4kp2/2npp3/1Nn5/1p2PQP1/7q/1PP1B3/4KR1r b - - 0 1",
+            "h1f1",
+            0,
+        ),
+        (
+            "8/4kp2/2npp3/1Nn5/1p2P1P1/7q/1PP1B3/4KR1r b - - 0 1",
+            "h1f1",
+            0,
+        ),
+        (
+            "2r2r1k/6bp/p7/2q2p1Q/3PpP2/1B6/P5PP/2RR3K b - - 0 1",
+            "c5c1",
+            2 * R - Q,
+        ),
+        (
+            "r2qk1nr/pp2ppbp/2b3p1/2p1p3/8/2N2N2/PPPP1PPP/R1BQR1K1 w kq - 0 1",
+            "f3e5",
+            P,
+        ),
+        // f6 is pinned, so white comes out a pawn up after dxe5 Rxe5
+        (
+            "6r1/4kq2/b2p1p2/p1pPb3/p1P2B1Q/2P4P/2B1R1P1/6K1 w - - 0 1",
+            "f4e5",
+            P,
+        ),
+        (
+            "3q2nk/pb1r1p2/np6/3P2Pp/2p1P3/2R4B/PQ3P1P/3R2K1 w - h6 0 1",
+            "g5h6",
+            0,
+        ),
+        (
+            "3q2nk/pb1r1p2/np6/3P2Pp/2p1P3/2R1B2B/PQ3P1P/3R2K1 w - h6 0 1",
+            "g5h6",
+            P,
+        ),
+        (
+            "2r4r/1P4pk/p2p1b1p/7n/BB3p2/2R2p2/P1P2P2/4RK2 w - - 0 1",
+            "c3c8",
+            R,
+        ),
+        (
+            "2r5/1P4pk/p2p1b1p/5b1n/BB3p2/2R2p2/P1P2P2/4RK2 w - - 0 1",
+            "c3c8",
+            R,
+        ),
+        (
+            "2r4k/2r4p/p7/2b2p1b/4pP2/1BR5/P1R3PP/2Q4K w - - 0 1",
+            "c3c5",
+            B,
+        ),
+        (
+            "8/pp6/2pkp3/4bp2/2R3b1/2P5/PP4B1/1K6 w - - 0 1",
+            "g2c6",
+            P - B,
+        ),
+        // quiet moves and en passant
+        ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", "e2e4", 0),
+        ("4k3/8/3p4/8/8/8/4N3/4K3 w - - 0 1", "e2c3", 0),
+        ("4k3/8/3p4/8/8/3N4/8/4K3 w - - 0 1", "d3e5", -N),
+        ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", P),
+        ("4k3/2p5/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 0),
+        // pins and the king
+        ("4k3/4n3/8/3p4/5N2/8/8/4R1K1 w - - 0 1", "f4d5", P),
+        ("k7/3p4/4r3/8/8/4R3/8/4K3 w - - 0 1", "e3e6", 0),
+        ("3rk3/3r4/8/8/8/8/8/3RK3 b - - 0 1", "d7d1", R),
+        ("4k3/3r4/8/8/8/8/8/3RK3 b - - 0 1", "d7d1", 0),
+    ];
+
+    #[test]
+    fn known_exchanges() {
+        for (fen, uci, expected) in SUITE {
+            let (pos, castling) = Position::try_parse_fen(fen).unwrap();
+            let mov = pos.parse_uci_move(uci, &castling).unwrap();
+
+            assert_eq!(pos.see_value(mov), expected, "{fen} {uci}");
+            assert!(pos.see(mov, expected), "{fen} {uci}");
+            assert!(!pos.see(mov, expected + 1), "{fen} {uci}");
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use super::{
+    consts::{Piece, Side},
+    frc::Castling,
+    moves::Move,
+    position::Position,
+};
+
+/// How a game is over, judged from its final position and history.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameOutcome {
+    /// The side to move is checkmated.
+    Checkmate,
+    Stalemate,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    ThreefoldRepetition,
+}
+
+impl GameOutcome {
+    /// Result from white's point of view, given the side to move in the
+    /// final position.
+    pub fn result(&self, stm: usize) -> f32 {
+        match self {
+            Self::Checkmate if stm == Side::WHITE => 0.0,
+            Self::Checkmate => 1.0,
+            _ => 0.5,
+        }
+    }
+}
+
+impl std::fmt::Display for GameOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Checkmate => "checkmate",
+            Self::Stalemate => "stalemate",
+            Self::FiftyMoveRule => "fifty-move rule",
+            Self::InsufficientMaterial => "insufficient material",
+            Self::ThreefoldRepetition => "threefold repetition",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// A position along with the hashes of the positions before it, so that
+/// repetitions can be detected as moves are played.
+#[derive(Clone)]
+pub struct GameState {
+    pos: Position,
+    castling: Castling,
+    hashes: Vec<u64>,
+}
+
+impl GameState {
+    pub fn new(pos: Position, castling: Castling) -> Self {
+        Self {
+            pos,
+            castling,
+            hashes: vec![pos.hash(&castling)],
+        }
+    }
+
+    pub fn position(&self) -> &Position {
+        &self.pos
+    }
+
+    pub fn castling(&self) -> &Castling {
+        &self.castling
+    }
+
+    /// Number of moves played since `new`.
+    pub fn plies(&self) -> usize {
+        self.hashes.len() - 1
+    }
+
+    pub fn make(&mut self, mov: Move) {
+        self.pos.make(mov, &self.castling);
+        self.hashes.push(self.pos.hash(&self.castling));
+    }
+
+    /// Number of times the current position occurred before, looking back
+    /// no further than the last capture or pawn move.
+    pub fn repetitions(&self) -> usize {
+        let hash = self.hashes[self.plies()];
+        let reversible = usize::from(self.pos.halfm()).min(self.plies());
+
+        self.hashes
+            .iter()
+            .rev()
+            .take(reversible + 1)
+            .skip(2)
+            .step_by(2)
+            .filter(|&&earlier| earlier == hash)
+            .count()
+    }
+
+    /// `None` while the game can go on. Checkmate and stalemate take
+    /// precedence over the draws by rule.
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        let mut has_moves = false;
+        self.pos
+            .map_legal_moves(&self.castling, |_| has_moves = true);
+
+        if !has_moves {
+            return Some(if self.pos.in_check() {
+                GameOutcome::Checkmate
+            } else {
+                GameOutcome::Stalemate
+            });
+        }
+
+        if self.pos.is_insufficient_material() {
+            Some(GameOutcome::InsufficientMaterial)
+        } else if self.repetitions() >= 2 {
+            Some(GameOutcome::ThreefoldRepetition)
+        } else if self.pos.halfm() >= 100 {
+            Some(GameOutcome::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+}
+
+impl Position {
+    /// Neither side has mating material: at most a single minor piece, or
+    /// only bishops, all on squares of the same colour.
+    #[must_use]
+    pub fn is_insufficient_material(&self) -> bool {
+        const LIGHT: u64 = 0x55AA_55AA_55AA_55AA;
+
+        let heavy = self.piece(Piece::PAWN) | self.piece(Piece::ROOK) | self.piece(Piece::QUEEN);
+        let knights = self.piece(Piece::KNIGHT);
+        let bishops = self.piece(Piece::BISHOP);
+
+        heavy == 0
+            && ((knights | bishops).count_ones() <= 1
+                || (knights == 0 && (bishops & LIGHT == 0 || bishops & !LIGHT == 0)))
+    }
+}
@@ -0,0 +1,88 @@
+use super::{consts::Flag, frc::Castling, moves::Move, position::Position};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UciMoveError {
+    /// The text is not shaped like a UCI move.
+    Invalid,
+    /// No legal move matches.
+    Illegal,
+    /// More than one legal move matches, as when castling is written as the
+    /// king's destination and that is also an ordinary king move.
+    Ambiguous,
+}
+
+impl std::fmt::Display for UciMoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid => write!(f, "not a valid UCI move"),
+            Self::Illegal => write!(f, "no legal move matches"),
+            Self::Ambiguous => write!(f, "more than one legal move matches"),
+        }
+    }
+}
+
+impl std::error::Error for UciMoveError {}
+
+fn parse_square(chars: &[u8]) -> Option<u16> {
+    match chars {
+        [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => {
+            Some(u16::from(8 * (rank - b'1') + file - b'a'))
+        }
+        _ => None,
+    }
+}
+
+impl Position {
+    /// Inverse of `Move::to_uci`. Castling is accepted as the king moving
+    /// onto its own rook, or as the king's destination square in standard
+    /// chess, where that cannot be mistaken for an ordinary king move. Text
+    /// that matches more than one legal move is rejected as ambiguous.
+    pub fn parse_uci_move(&self, uci: &str, castling: &Castling) -> Result<Move, UciMoveError> {
+        let bytes = uci.trim().as_bytes();
+
+        if !(4..=5).contains(&bytes.len()) {
+            return Err(UciMoveError::Invalid);
+        }
+
+        let src = parse_square(&bytes[..2]).ok_or(UciMoveError::Invalid)?;
+        let to = parse_square(&bytes[2..4]).ok_or(UciMoveError::Invalid)?;
+
+        let promo = match bytes.get(4).map(u8::to_ascii_lowercase) {
+            None => None,
+            Some(ch) => Some(
+                b"nbrq"
+                    .iter()
+                    .position(|&pc| pc == ch)
+                    .ok_or(UciMoveError::Invalid)?
+                    + 3,
+            ),
+        };
+
+        let mut found = Err(UciMoveError::Illegal);
+
+        self.map_legal_moves(castling, |mov| {
+            if mov.src() != src {
+                return;
+            }
+
+            let matches = if [Flag::KS, Flag::QS].contains(&mov.flag()) {
+                let side = self.stm();
+                let rook = 56 * side as u16
+                    + castling.rook_file(side, usize::from(mov.flag() == Flag::KS));
+
+                promo.is_none() && (to == rook || (!castling.is_chess960() && to == mov.to()))
+            } else {
+                mov.to() == to && promo == mov.is_promo().then(|| mov.promo_pc())
+            };
+
+            if matches {
+                found = match found {
+                    Err(UciMoveError::Illegal) => Ok(mov),
+                    _ => Err(UciMoveError::Ambiguous),
+                };
+            }
+        });
+
+        found
+    }
+}
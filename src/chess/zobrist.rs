@@ -0,0 +1,31 @@
+use crate::init;
+
+/// Fixed keys, so hashes are the same across runs and builds.
+pub(super) struct Zobrist {
+    /// Indexed like `Position`'s bitboards, with no key for the colours.
+    pub pieces: [[[u64; 64]; 8]; 2],
+    pub stm: u64,
+    /// Indexed by the square of the rook a castling right refers to.
+    pub castling: [u64; 64],
+    /// Indexed by the file of the en passant square.
+    pub enp: [u64; 8],
+}
+
+pub(super) static ZOBRIST: Zobrist = Zobrist {
+    pieces: init!(|side, 2| init!(|pc, 8| init!(|sq, 64| if pc < 2 {
+        0
+    } else {
+        key(64 * (6 * side + pc - 2) + sq)
+    }))),
+    stm: key(768),
+    castling: init!(|sq, 64| key(769 + sq)),
+    enp: init!(|file, 8| key(833 + file)),
+};
+
+// splitmix64
+const fn key(idx: usize) -> u64 {
+    let mut z = (idx as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,461 @@
+use std::io::{BufRead, ErrorKind, Read, Write};
+
+use crate::MontyFormatError;
+
+/// Games are gathered into blocks of at least this many bytes before being
+/// compressed, so a block only exceeds it by the size of its last game.
+pub(crate) const BLOCK_SIZE: usize = 1 << 20;
+
+/// Largest decompressed block a reader will accept.
+const MAX_BLOCK_SIZE: usize = 1 << 26;
+
+const PROB_BITS: u32 = 11;
+const PROB_ONE: u16 = 1 << PROB_BITS;
+const ADAPT_SHIFT: u32 = 5;
+const TOP: u32 = 1 << 24;
+
+/// Bitwise adaptive model, predicting each byte from the one before it.
+struct Model {
+    probs: Vec<u16>,
+    prev: usize,
+}
+
+impl Model {
+    fn new() -> Self {
+        Self {
+            probs: vec![PROB_ONE / 2; 256 * 256],
+            prev: 0,
+        }
+    }
+
+    fn context(&mut self) -> &mut [u16] {
+        &mut self.probs[self.prev * 256..][..256]
+    }
+}
+
+struct Encoder<'a> {
+    out: &'a mut Vec<u8>,
+    low: u64,
+    range: u32,
+    cache: u8,
+    cache_size: u64,
+}
+
+impl<'a> Encoder<'a> {
+    fn new(out: &'a mut Vec<u8>) -> Self {
+        Self {
+            out,
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            cache_size: 1,
+        }
+    }
+
+    fn encode(&mut self, prob: &mut u16, bit: bool) {
+        let bound = (self.range >> PROB_BITS) * u32::from(*prob);
+
+        if bit {
+            self.low += u64::from(bound);
+            self.range -= bound;
+            *prob -= *prob >> ADAPT_SHIFT;
+        } else {
+            self.range = bound;
+            *prob += (PROB_ONE - *prob) >> ADAPT_SHIFT;
+        }
+
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low();
+        }
+    }
+
+    fn shift_low(&mut self) {
+        if self.low < 0xFF00_0000 || self.low > u64::from(u32::MAX) {
+            let carry = (self.low >> 32) as u8;
+            let mut byte = self.cache;
+
+            while self.cache_size > 0 {
+                self.out.push(byte.wrapping_add(carry));
+                byte = 0xFF;
+                self.cache_size -= 1;
+            }
+
+            self.cache = (self.low >> 24) as u8;
+        }
+
+        self.cache_size += 1;
+        self.low = (self.low & 0x00FF_FFFF) << 8;
+    }
+
+    fn finish(mut self) {
+        for _ in 0..5 {
+            self.shift_low();
+        }
+    }
+}
+
+struct Decoder<'a> {
+    input: &'a [u8],
+    pos: usize,
+    code: u32,
+    range: u32,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        let mut ret = Self {
+            input,
+            pos: 0,
+            code: 0,
+            range: u32::MAX,
+        };
+
+        for _ in 0..5 {
+            ret.code = (ret.code << 8) | u32::from(ret.next_byte());
+        }
+
+        ret
+    }
+
+    /// Past the end of the input, reads as zero; `overran` reports it.
+    fn next_byte(&mut self) -> u8 {
+        let byte = self.input.get(self.pos).copied().unwrap_or(0);
+        self.pos += 1;
+        byte
+    }
+
+    fn overran(&self) -> bool {
+        self.pos > self.input.len()
+    }
+
+    fn decode(&mut self, prob: &mut u16) -> bool {
+        let bound = (self.range >> PROB_BITS) * u32::from(*prob);
+
+        let bit = if self.code < bound {
+            self.range = bound;
+            *prob += (PROB_ONE - *prob) >> ADAPT_SHIFT;
+            false
+        } else {
+            self.code -= bound;
+            self.range -= bound;
+            *prob -= *prob >> ADAPT_SHIFT;
+            true
+        };
+
+        while self.range < TOP {
+            self.range <<= 8;
+            self.code = (self.code << 8) | u32::from(self.next_byte());
+        }
+
+        bit
+    }
+}
+
+fn compress(raw: &[u8], out: &mut Vec<u8>) {
+    let mut model = Model::new();
+    let mut encoder = Encoder::new(out);
+
+    for &byte in raw {
+        let probs = model.context();
+        let mut node = 1;
+
+        for i in (0..8).rev() {
+            let bit = (byte >> i) & 1 == 1;
+            encoder.encode(&mut probs[node], bit);
+            node = 2 * node + usize::from(bit);
+        }
+
+        model.prev = usize::from(byte);
+    }
+
+    encoder.finish();
+}
+
+fn decompress(payload: &[u8], raw_len: usize, out: &mut Vec<u8>) -> Result<(), &'static str> {
+    let mut model = Model::new();
+    let mut decoder = Decoder::new(payload);
+
+    for _ in 0..raw_len {
+        let probs = model.context();
+        let mut node = 1;
+
+        while node < 256 {
+            node = 2 * node + usize::from(decoder.decode(&mut probs[node]));
+        }
+
+        let byte = (node - 256) as u8;
+        out.push(byte);
+        model.prev = usize::from(byte);
+    }
+
+    if decoder.overran() {
+        return Err("compressed block ends early");
+    }
+
+    Ok(())
+}
+
+/// Compresses `raw` as a single block: compressed length (u32), raw length
+/// (u32), then the range-coded payload.
+pub(crate) fn write_block(
+    writer: &mut impl Write,
+    raw: &[u8],
+    scratch: &mut Vec<u8>,
+) -> Result<(), MontyFormatError> {
+    scratch.clear();
+    compress(raw, scratch);
+
+    writer.write_all(&(scratch.len() as u32).to_le_bytes())?;
+    writer.write_all(&(raw.len() as u32).to_le_bytes())?;
+    writer.write_all(scratch)?;
+    Ok(())
+}
+
+/// Reads a whole block, still compressed, into `buffer`, returning `false`
+/// if the stream ends cleanly before it.
+pub(crate) fn read_raw_block(
+    reader: &mut impl BufRead,
+    buffer: &mut Vec<u8>,
+) -> std::io::Result<bool> {
+    buffer.clear();
+
+    if reader.fill_buf()?.is_empty() {
+        return Ok(false);
+    }
+
+    let mut lengths = [0; 8];
+    reader.read_exact(&mut lengths).map_err(truncated)?;
+    buffer.extend_from_slice(&lengths);
+
+    let len = u32::from_le_bytes([lengths[0], lengths[1], lengths[2], lengths[3]]) as usize;
+    let raw_len = u32::from_le_bytes([lengths[4], lengths[5], lengths[6], lengths[7]]) as usize;
+
+    if raw_len > MAX_BLOCK_SIZE || len > 2 * MAX_BLOCK_SIZE {
+        return Err(invalid("compressed block too large"));
+    }
+
+    buffer.resize(8 + len, 0);
+    reader.read_exact(&mut buffer[8..]).map_err(truncated)?;
+    Ok(true)
+}
+
+/// A truncated block must not look like a clean end of stream to callers
+/// that stop at `UnexpectedEof`.
+fn truncated(err: std::io::Error) -> std::io::Error {
+    if err.kind() == ErrorKind::UnexpectedEof {
+        invalid("truncated compressed block")
+    } else {
+        err
+    }
+}
+
+fn invalid(reason: &'static str) -> std::io::Error {
+    std::io::Error::new(ErrorKind::InvalidData, reason)
+}
+
+/// Presents the games of a stream as plain bytes, decompressing block by
+/// block if the file header has `FileHeader::COMPRESSED` set, and passing
+/// reads straight through otherwise. Expects to be positioned after the
+/// header.
+pub struct BlockReader<R> {
+    inner: R,
+    compressed: bool,
+    block: Vec<u8>,
+    pos: usize,
+    scratch: Vec<u8>,
+}
+
+impl<R: BufRead> BlockReader<R> {
+    pub fn new(inner: R, compressed: bool) -> Self {
+        Self {
+            inner,
+            compressed,
+            block: Vec::new(),
+            pos: 0,
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn next_block(&mut self) -> std::io::Result<()> {
+        self.block.clear();
+        self.pos = 0;
+
+        if read_raw_block(&mut self.inner, &mut self.scratch)? {
+            let raw_len = u32::from_le_bytes(self.scratch[4..8].try_into().unwrap());
+            decompress(&self.scratch[8..], raw_len as usize, &mut self.block).map_err(invalid)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for BlockReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.fill_buf()?.read(buf)?;
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for BlockReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if !self.compressed {
+            return self.inner.fill_buf();
+        }
+
+        // an empty block is skipped rather than taken as the end of stream
+        while self.pos == self.block.len() {
+            self.next_block()?;
+
+            if self.block.is_empty() && self.inner.fill_buf()?.is_empty() {
+                break;
+            }
+        }
+
+        Ok(&self.block[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.compressed {
+            self.pos += amt;
+        } else {
+            self.inner.consume(amt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chess::{Castling, Position, STARTPOS},
+        GameReader, GameWriter, MontyFormat, SearchData,
+    };
+
+    fn round_trip(raw: &[u8]) -> Vec<u8> {
+        let mut payload = Vec::new();
+        compress(raw, &mut payload);
+
+        let mut out = Vec::new();
+        decompress(&payload, raw.len(), &mut out).unwrap();
+        out
+    }
+
+    fn noise(len: usize) -> Vec<u8> {
+        let mut seed = 0x9E37_79B9_7F4A_7C15u64;
+
+        (0..len)
+            .map(|i| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+
+                // mostly structured, so the model has something to learn
+                if i % 4 == 0 {
+                    seed as u8
+                } else {
+                    (i / 64) as u8
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty_input() {
+        assert!(round_trip(&[]).is_empty());
+    }
+
+    #[test]
+    fn runs_of_ff_bytes() {
+        // leaves long runs of pending 0xFF bytes in the encoder
+        let raw = vec![0xFF; 100_000];
+        assert_eq!(round_trip(&raw), raw);
+
+        // and the odd surprise in between carries into them
+        let raw: Vec<u8> = (0..100_000)
+            .map(|i| if i % 97 == 0 { 0 } else { 0xFF })
+            .collect();
+        assert_eq!(round_trip(&raw), raw);
+    }
+
+    #[test]
+    fn larger_than_a_block() {
+        let raw = noise(BLOCK_SIZE + 12345);
+        assert_eq!(round_trip(&raw), raw);
+    }
+
+    #[test]
+    fn truncated_payload() {
+        let raw = noise(4096);
+        let mut payload = Vec::new();
+        compress(&raw, &mut payload);
+        payload.truncate(payload.len() / 2);
+
+        let mut out = Vec::new();
+        assert_eq!(
+            decompress(&payload, raw.len(), &mut out),
+            Err("compressed block ends early")
+        );
+    }
+
+    #[test]
+    fn compressed_games_round_trip() {
+        let mut castling = Castling::default();
+        let startpos = Position::parse_fen(STARTPOS, &mut castling);
+
+        let games: Vec<MontyFormat> = (0..20)
+            .map(|n| {
+                let mut game = MontyFormat::new(startpos, castling);
+                let mut pos = startpos;
+
+                for ply in 0..10 + n {
+                    let mut legal = Vec::new();
+                    pos.map_legal_moves(&castling, |mov| legal.push(mov));
+
+                    let mov = legal[(n * 7 + ply * 3) % legal.len()];
+                    let dist = legal
+                        .iter()
+                        .map(|&mov| (mov, u32::from(u16::from(mov) % 97)));
+
+                    game.push(SearchData::new(mov, 0.5, Some(dist.collect())));
+                    pos.make(mov, &castling);
+                }
+
+                game.result = 0.5;
+                game
+            })
+            .collect();
+
+        let mut writer = GameWriter::compressed(Vec::new()).unwrap();
+        for game in &games {
+            writer.write(game).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+
+        let reader: GameReader<_, MontyFormat> = GameReader::new(&bytes[..]).unwrap();
+        assert!(reader.header().unwrap().is_compressed());
+
+        let read = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(read.len(), games.len());
+
+        // visits are scaled on write, so compare what each game encodes to
+        for (read, game) in read.iter().zip(&games) {
+            let mut expected = Vec::new();
+            game.serialise_into_buffer(&mut expected).unwrap();
+
+            let mut found = Vec::new();
+            read.serialise_into_buffer(&mut found).unwrap();
+
+            assert_eq!(found, expected);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::io::{BufRead, Write};
+
+use crate::{
+    FileHeader, FormatKind, GameReader, GameWriter, MontyFormat, MontyFormatError, MontyValueFormat,
+};
+
+/// Options for deriving value training data from a `MontyFormat` stream.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConvertOptions {
+    /// Plies at the start of each game to leave out. The converted game
+    /// starts from the position reached after them.
+    pub skip_plies: usize,
+}
+
+impl MontyFormat {
+    /// Copy of the game without policy data, with each score converted as
+    /// `MontyValueFormat::push` does.
+    pub fn to_value_format(&self) -> MontyValueFormat {
+        let mut value = MontyValueFormat {
+            startpos: self.startpos,
+            castling: self.castling,
+            result: self.result,
+            moves: Vec::with_capacity(self.moves.len()),
+        };
+
+        for (pos, data, _, _) in self.replay() {
+            value.push_with_wdl(pos.stm(), data.best_move, data.score, data.wdl);
+        }
+
+        value
+    }
+
+    /// Removes the first `plies` moves, advancing `startpos` past them.
+    pub fn skip_opening(&mut self, plies: usize) {
+        let plies = plies.min(self.moves.len());
+
+        for data in &self.moves[..plies] {
+            self.startpos.make(data.best_move, &self.castling);
+        }
+
+        self.moves.drain(..plies);
+    }
+}
+
+/// Converts every game of a `MontyFormat` stream, writing a new file with a
+/// header to `writer`, compressed if the input was. Games left without moves
+/// after skipping are dropped. Returns the number of games written.
+pub fn convert_to_value(
+    mut reader: GameReader<impl BufRead, MontyFormat>,
+    writer: impl Write,
+    options: ConvertOptions,
+) -> Result<u64, MontyFormatError> {
+    let header = match reader.header() {
+        Some(header) if header.is_compressed() => FileHeader::compressed(FormatKind::Value),
+        _ => FileHeader::new(FormatKind::Value),
+    };
+
+    let mut writer = GameWriter::with_header(writer, header)?;
+
+    while let Some(game) = reader.next() {
+        let mut game = game?;
+        game.skip_opening(options.skip_plies);
+
+        if !game.moves.is_empty() {
+            writer.write(&game.to_value_format())?;
+        }
+
+        reader.recycle(game);
+    }
+
+    let games = writer.games_written();
+    writer.finish()?;
+    Ok(games)
+}
@@ -0,0 +1,244 @@
+use std::io::ErrorKind;
+
+use crate::{chess::Move, FormatKind, Wdl};
+
+/// Errors from reading or writing games. Decoding errors carry the byte
+/// offset of the offending game (relative to the start of the stream, when
+/// known) and the ply at which decoding failed.
+#[derive(Debug)]
+pub enum MontyFormatError {
+    Io(std::io::Error),
+    Truncated {
+        offset: u64,
+        ply: Option<usize>,
+    },
+    IllegalMove {
+        offset: u64,
+        ply: usize,
+        mov: Move,
+        fen: String,
+    },
+    VisitCountMismatch {
+        offset: u64,
+        ply: usize,
+        stored: usize,
+        legal: usize,
+        fen: String,
+    },
+    ScoreOutOfRange {
+        ply: usize,
+        score: f32,
+    },
+    MissingPayload {
+        ply: usize,
+        payload: &'static str,
+    },
+    InvalidWdl {
+        ply: usize,
+        wdl: Wdl,
+    },
+    InvalidResult {
+        offset: u64,
+        result: f32,
+    },
+    InvalidPosition {
+        offset: u64,
+        reason: &'static str,
+    },
+    UnsupportedGameFlags {
+        offset: u64,
+        flags: u8,
+    },
+    InvalidEncoding {
+        offset: u64,
+        reason: &'static str,
+    },
+    InvalidPgn {
+        line: usize,
+        ply: Option<usize>,
+        reason: String,
+    },
+    InvalidText {
+        line: usize,
+        reason: String,
+    },
+    NonEmptyBuffer,
+    InvalidHeader(&'static str),
+    UnsupportedVersion(u16),
+    UnsupportedFlags(u32),
+    FormatMismatch {
+        expected: FormatKind,
+        found: FormatKind,
+    },
+}
+
+impl MontyFormatError {
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            Self::Truncated { offset, .. }
+            | Self::IllegalMove { offset, .. }
+            | Self::VisitCountMismatch { offset, .. }
+            | Self::InvalidResult { offset, .. }
+            | Self::InvalidPosition { offset, .. }
+            | Self::UnsupportedGameFlags { offset, .. }
+            | Self::InvalidEncoding { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    pub fn ply(&self) -> Option<usize> {
+        match self {
+            Self::Truncated { ply, .. } | Self::InvalidPgn { ply, .. } => *ply,
+            Self::IllegalMove { ply, .. }
+            | Self::VisitCountMismatch { ply, .. }
+            | Self::ScoreOutOfRange { ply, .. }
+            | Self::MissingPayload { ply, .. }
+            | Self::InvalidWdl { ply, .. } => Some(*ply),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn at_offset(mut self, game_offset: u64) -> Self {
+        match &mut self {
+            Self::Truncated { offset, .. }
+            | Self::IllegalMove { offset, .. }
+            | Self::VisitCountMismatch { offset, .. }
+            | Self::InvalidResult { offset, .. }
+            | Self::InvalidPosition { offset, .. }
+            | Self::UnsupportedGameFlags { offset, .. }
+            | Self::InvalidEncoding { offset, .. } => *offset += game_offset,
+            _ => {}
+        }
+
+        self
+    }
+
+    pub(crate) fn at_ply(mut self, at: usize) -> Self {
+        if let Self::Truncated { ply, .. } = &mut self {
+            ply.get_or_insert(at);
+        }
+
+        self
+    }
+}
+
+impl std::fmt::Display for MontyFormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Truncated { offset, ply: None } => {
+                write!(f, "Game at byte offset {offset} is truncated!")
+            }
+            Self::Truncated {
+                offset,
+                ply: Some(ply),
+            } => write!(
+                f,
+                "Game at byte offset {offset} is truncated at ply {ply}!"
+            ),
+            Self::IllegalMove {
+                offset,
+                ply,
+                mov,
+                fen,
+            } => write!(
+                f,
+                "Game at byte offset {offset} has illegal move {mov} at ply {ply} ({fen})!"
+            ),
+            Self::VisitCountMismatch {
+                offset,
+                ply,
+                stored,
+                legal,
+                fen,
+            } => write!(
+                f,
+                "Game at byte offset {offset} stores {stored} visit counts for {legal} legal moves at ply {ply} ({fen})!"
+            ),
+            Self::ScoreOutOfRange { ply, score } => {
+                write!(f, "Score {score} at ply {ply} is outside valid range!")
+            }
+            Self::MissingPayload { ply, payload } => write!(
+                f,
+                "Move at ply {ply} has no {payload} but other moves in the game do!"
+            ),
+            Self::InvalidWdl { ply, wdl } => write!(
+                f,
+                "WDL {:.3}/{:.3}/{:.3} at ply {ply} is not a valid distribution!",
+                wdl.win, wdl.draw, wdl.loss
+            ),
+            Self::InvalidResult { offset, result } => {
+                write!(f, "Game at byte offset {offset} has invalid result {result}!")
+            }
+            Self::InvalidPosition { offset, reason } => {
+                write!(f, "Game at byte offset {offset} has invalid position: {reason}!")
+            }
+            Self::UnsupportedGameFlags { offset, flags } => write!(
+                f,
+                "Game at byte offset {offset} has unsupported flags {flags:#x}!"
+            ),
+            Self::InvalidEncoding { offset, reason } => {
+                write!(f, "Game at byte offset {offset} is badly encoded: {reason}!")
+            }
+            Self::InvalidPgn {
+                line,
+                ply: None,
+                reason,
+            } => write!(f, "PGN game at line {line} is invalid: {reason}!"),
+            Self::InvalidPgn {
+                line,
+                ply: Some(ply),
+                reason,
+            } => write!(
+                f,
+                "PGN game at line {line} is invalid at ply {ply}: {reason}!"
+            ),
+            Self::InvalidText { line, reason } => {
+                write!(f, "Line {line} is invalid: {reason}!")
+            }
+            Self::NonEmptyBuffer => write!(f, "Buffer is not empty!"),
+            Self::InvalidHeader(reason) => write!(f, "Invalid file header: {reason}!"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported format version {version}!")
+            }
+            Self::UnsupportedFlags(flags) => write!(f, "Unsupported header flags {flags:#x}!"),
+            Self::FormatMismatch { expected, found } => {
+                write!(f, "Expected a {expected} file, found a {found} file!")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MontyFormatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for MontyFormatError {
+    fn from(err: std::io::Error) -> Self {
+        if err.kind() == ErrorKind::UnexpectedEof {
+            Self::Truncated {
+                offset: 0,
+                ply: None,
+            }
+        } else {
+            Self::Io(err)
+        }
+    }
+}
+
+impl From<MontyFormatError> for std::io::Error {
+    fn from(err: MontyFormatError) -> Self {
+        match err {
+            MontyFormatError::Io(err) => err,
+            MontyFormatError::Truncated { .. } => {
+                std::io::Error::new(ErrorKind::UnexpectedEof, err.to_string())
+            }
+            _ => std::io::Error::new(ErrorKind::InvalidData, err.to_string()),
+        }
+    }
+}
@@ -1,15 +1,111 @@
-use std::io::{Error, ErrorKind, Write};
+use std::io::{ErrorKind, Write};
 
 use crate::{
-    chess::{Castling, Move, Position},
+    bitloop,
+    chess::{Castling, FenOptions, Move, Piece, Position, Side},
     interleave::{interleave, FastDeserialise},
-    read_into_primitive, read_primitive_into_vec,
+    read_into_primitive, read_primitive_into_vec, FormatKind, GameMetadata, MontyFormatError,
 };
 
+// per-game flags, packed into the result byte above the result itself
+pub(crate) struct GameFlag;
+impl GameFlag {
+    pub const RESULT: u8 = 0b11;
+    pub const U16_VISITS: u8 = 0b100;
+    pub const WDL: u8 = 0b1000;
+    pub const STATS: u8 = 0b1_0000;
+    pub const METADATA: u8 = 0b10_0000;
+    pub const COMPACT_MOVES: u8 = 0b100_0000;
+}
+
+// per-move tags of the compact move encoding
+struct MoveTag;
+impl MoveTag {
+    const END: u8 = 0;
+    const NO_VISITS: u8 = 1;
+    const VISITS: u8 = 2;
+    const MOST_VISITED: u8 = 3;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VisitPrecision {
+    /// Visits scaled so that the most visited move is 255.
+    #[default]
+    U8,
+    /// Raw visit counts, scaled down only if the most visited move exceeds
+    /// `u16::MAX`.
+    U16,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MoveEncoding {
+    /// Each best move stored in full, followed by the number of visit counts.
+    #[default]
+    Standard,
+    /// Each best move stored as a 1-byte index into the legal moves sorted
+    /// as in the visit distribution, or omitted when it is the most visited
+    /// move. Finding the end of a game then requires replaying it.
+    Compact,
+}
+
+/// Win/draw/loss probabilities, from the same perspective as the score they
+/// accompany. Stored as quantised win and draw probabilities.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Wdl {
+    pub win: f32,
+    pub draw: f32,
+    pub loss: f32,
+}
+
+impl Wdl {
+    pub fn new(win: f32, draw: f32, loss: f32) -> Self {
+        Self { win, draw, loss }
+    }
+
+    #[must_use]
+    pub fn expected_score(&self) -> f32 {
+        self.win + self.draw / 2.0
+    }
+
+    #[must_use]
+    pub fn flipped(&self) -> Self {
+        Self::new(self.loss, self.draw, self.win)
+    }
+
+    pub(crate) fn is_valid(&self) -> bool {
+        let probs = [self.win, self.draw, self.loss];
+        probs.iter().all(|p| (0.0..=1.0).contains(p))
+            && (probs.iter().sum::<f32>() - 1.0).abs() < 1e-3
+    }
+
+    pub(crate) fn to_raw(self) -> [u16; 2] {
+        let quantise = |p: f32| (p * f32::from(u16::MAX)).round() as u16;
+        [quantise(self.win), quantise(self.draw)]
+    }
+
+    pub(crate) fn from_raw(raw: [u16; 2]) -> Self {
+        let win = f32::from(raw[0]) / f32::from(u16::MAX);
+        let draw = f32::from(raw[1]) / f32::from(u16::MAX);
+        Self::new(win, draw, (1.0 - win - draw).max(0.0))
+    }
+}
+
+/// How much search went into a recorded move. Stored as
+/// nodes (varint), average depth (1 byte), max depth (1 byte), time (varint).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    pub nodes: u64,
+    pub avg_depth: u8,
+    pub max_depth: u8,
+    pub time_ms: u64,
+}
+
 pub struct SearchData {
     pub best_move: Move,
     pub score: f32,
     pub visit_distribution: Option<Vec<(Move, u32)>>,
+    pub wdl: Option<Wdl>,
+    pub stats: Option<SearchStats>,
 }
 
 impl SearchData {
@@ -32,8 +128,28 @@ impl SearchData {
             best_move: best_move.into(),
             score,
             visit_distribution,
+            wdl: None,
+            stats: None,
         }
     }
+
+    #[must_use]
+    pub fn with_wdl(mut self, wdl: Wdl) -> Self {
+        self.wdl = Some(wdl);
+        self
+    }
+
+    #[must_use]
+    pub fn with_stats(mut self, stats: SearchStats) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Expected score derived from the WDL payload if present, otherwise the
+    /// stored score.
+    pub fn expected_score(&self) -> f32 {
+        self.wdl.map_or(self.score, |wdl| wdl.expected_score())
+    }
 }
 
 pub struct MontyFormat {
@@ -41,6 +157,9 @@ pub struct MontyFormat {
     pub castling: Castling,
     pub result: f32,
     pub moves: Vec<SearchData>,
+    pub visit_precision: VisitPrecision,
+    pub move_encoding: MoveEncoding,
+    pub metadata: Option<GameMetadata>,
 }
 
 impl MontyFormat {
@@ -50,6 +169,9 @@ impl MontyFormat {
             castling,
             result: 0.0,
             moves: Vec::new(),
+            visit_precision: VisitPrecision::U8,
+            move_encoding: MoveEncoding::Standard,
+            metadata: None,
         }
     }
 
@@ -61,52 +183,55 @@ impl MontyFormat {
         self.moves.pop()
     }
 
-    pub fn serialise_into_buffer(&self, writer: &mut Vec<u8>) -> std::io::Result<()> {
+    pub fn serialise_into_buffer(&self, writer: &mut Vec<u8>) -> Result<(), MontyFormatError> {
         if !writer.is_empty() {
-            return Err(Error::new(ErrorKind::Other, "Buffer is not empty!"));
+            return Err(MontyFormatError::NonEmptyBuffer);
         }
 
-        let compressed = CompressedChessBoard::from(self.startpos);
+        let mut flags = match self.visit_precision {
+            VisitPrecision::U8 => 0,
+            VisitPrecision::U16 => GameFlag::U16_VISITS,
+        };
 
-        for bb in compressed.bbs {
-            writer.write_all(&bb.to_le_bytes())?;
+        if self.moves.iter().any(|data| data.wdl.is_some()) {
+            flags |= GameFlag::WDL;
         }
 
-        writer.write_all(&compressed.stm.to_le_bytes())?;
-        writer.write_all(&compressed.enp_sq.to_le_bytes())?;
-        writer.write_all(&compressed.rights.to_le_bytes())?;
-        writer.write_all(&compressed.halfm.to_le_bytes())?;
-        writer.write_all(&compressed.fullm.to_le_bytes())?;
+        if self.moves.iter().any(|data| data.stats.is_some()) {
+            flags |= GameFlag::STATS;
+        }
 
-        for side in self.castling.rook_files() {
-            for rook in side {
-                writer.write_all(&rook.to_le_bytes())?;
-            }
+        if self.metadata.is_some() {
+            flags |= GameFlag::METADATA;
         }
 
-        let result = (self.result * 2.0) as u8;
-        writer.write_all(&result.to_le_bytes())?;
+        let compact = self.move_encoding == MoveEncoding::Compact;
 
-        for data in &self.moves {
+        if compact {
+            flags |= GameFlag::COMPACT_MOVES;
+        }
+
+        write_game_header(writer, &self.startpos, &self.castling, self.result, flags)?;
+
+        if let Some(metadata) = &self.metadata {
+            metadata.write_into(writer)?;
+        }
+
+        let mut pos = self.startpos;
+        let mut legal = Vec::new();
+        let mut visits = Vec::new();
+
+        for (ply, data) in self.moves.iter().enumerate() {
             if data.score.clamp(0.0, 1.0) != data.score {
-                return Err(Error::new(
-                    ErrorKind::InvalidData,
-                    "Score outside valid range!",
-                ));
+                return Err(MontyFormatError::ScoreOutOfRange {
+                    ply,
+                    score: data.score,
+                });
             }
 
             let score = (data.score * f32::from(u16::MAX)) as u16;
 
-            writer.write_all(&u16::from(data.best_move).to_le_bytes())?;
-            writer.write_all(&score.to_le_bytes())?;
-
-            let num_moves = data
-                .visit_distribution
-                .as_ref()
-                .map(|dist| dist.len())
-                .unwrap_or(0) as u8;
-
-            writer.write_all(&num_moves.to_le_bytes())?;
+            visits.clear();
 
             if let Some(dist) = data.visit_distribution.as_ref() {
                 let max_visits = dist
@@ -114,117 +239,316 @@ impl MontyFormat {
                     .max_by_key(|(_, visits)| visits)
                     .map(|x| x.1)
                     .unwrap_or(0);
-                for (_, visits) in dist {
-                    let scaled_visits = (*visits as f32 * 256.0 / max_visits as f32) as u8;
-                    writer.write_all(&scaled_visits.to_le_bytes())?;
+                match self.visit_precision {
+                    VisitPrecision::U8 => {
+                        visits.extend(dist.iter().map(|(_, visits)| {
+                            u32::from((*visits as f32 * 256.0 / max_visits as f32) as u8)
+                        }));
+                    }
+                    VisitPrecision::U16 => {
+                        let scale = (f64::from(u16::MAX) / f64::from(max_visits)).min(1.0);
+                        visits.extend(
+                            dist.iter()
+                                .map(|(_, visits)| u32::from((f64::from(*visits) * scale) as u16)),
+                        );
+                    }
                 }
             }
+
+            if compact {
+                sorted_legal_moves(&pos, &self.castling, &mut legal);
+
+                let index = legal
+                    .iter()
+                    .position(|&mov| mov == data.best_move)
+                    .ok_or_else(|| MontyFormatError::IllegalMove {
+                        offset: 0,
+                        ply,
+                        mov: data.best_move,
+                        fen: pos.to_fen(&self.castling, FenOptions::default()),
+                    })?;
+
+                if visits.is_empty() {
+                    writer.write_all(&[MoveTag::NO_VISITS, index as u8])?;
+                } else if visits.len() != legal.len() {
+                    return Err(MontyFormatError::VisitCountMismatch {
+                        offset: 0,
+                        ply,
+                        stored: visits.len(),
+                        legal: legal.len(),
+                        fen: pos.to_fen(&self.castling, FenOptions::default()),
+                    });
+                } else if most_visited(visits.iter().copied()) == Some(index) {
+                    writer.write_all(&[MoveTag::MOST_VISITED])?;
+                } else {
+                    writer.write_all(&[MoveTag::VISITS, index as u8])?;
+                }
+
+                pos.make(data.best_move, &self.castling);
+            } else {
+                writer.write_all(&u16::from(data.best_move).to_le_bytes())?;
+            }
+
+            writer.write_all(&score.to_le_bytes())?;
+
+            if flags & GameFlag::WDL > 0 {
+                write_wdl(writer, data.wdl, ply)?;
+            }
+
+            if flags & GameFlag::STATS > 0 {
+                let stats = data.stats.ok_or(MontyFormatError::MissingPayload {
+                    ply,
+                    payload: "search stats",
+                })?;
+
+                write_varint(writer, stats.nodes)?;
+                writer.write_all(&[stats.avg_depth, stats.max_depth])?;
+                write_varint(writer, stats.time_ms)?;
+            }
+
+            if !compact {
+                writer.write_all(&[visits.len() as u8])?;
+            }
+
+            for &visits in &visits {
+                match self.visit_precision {
+                    VisitPrecision::U8 => writer.write_all(&[visits as u8])?,
+                    VisitPrecision::U16 => writer.write_all(&(visits as u16).to_le_bytes())?,
+                }
+            }
+        }
+
+        if compact {
+            writer.write_all(&[MoveTag::END])?;
+        } else {
+            writer.write_all(&[0; 2])?;
         }
 
-        writer.write_all(&[0; 2])?;
         Ok(())
     }
 
-    pub fn deserialise_from(reader: &mut impl std::io::BufRead) -> std::io::Result<Self> {
-        let mut bbs = [0u64; 4];
-        for bb in &mut bbs {
-            *bb = read_into_primitive!(reader, u64);
-        }
+    pub fn deserialise_from(reader: &mut impl std::io::BufRead) -> Result<Self, MontyFormatError> {
+        Self::deserialise_reusing(reader, Vec::new())
+    }
 
-        let stm = read_into_primitive!(reader, u8);
-        let enp_sq = read_into_primitive!(reader, u8);
-        let rights = read_into_primitive!(reader, u8);
-        let halfm = read_into_primitive!(reader, u8);
-        let fullm = read_into_primitive!(reader, u16);
+    pub fn deserialise_reusing(
+        reader: &mut impl std::io::BufRead,
+        buffer: Vec<SearchData>,
+    ) -> Result<Self, MontyFormatError> {
+        const KNOWN: u8 = GameFlag::U16_VISITS
+            | GameFlag::WDL
+            | GameFlag::STATS
+            | GameFlag::METADATA
+            | GameFlag::COMPACT_MOVES;
 
-        let compressed = CompressedChessBoard {
-            bbs,
-            stm,
-            enp_sq,
-            rights,
-            halfm,
-            fullm,
+        let (startpos, castling, result, flags) = read_game_header(reader, KNOWN)?;
+
+        let metadata = if flags & GameFlag::METADATA > 0 {
+            Some(GameMetadata::read_from(reader)?)
+        } else {
+            None
         };
-        let startpos = Position::from(compressed);
 
-        let mut rook_files = [[0; 2]; 2];
-        for side in &mut rook_files {
-            for rook in side {
-                *rook = read_into_primitive!(reader, u8);
-            }
-        }
+        let visit_precision = if flags & GameFlag::U16_VISITS > 0 {
+            VisitPrecision::U16
+        } else {
+            VisitPrecision::U8
+        };
 
-        let castling = Castling::from_raw(&startpos, rook_files);
+        let move_encoding = if flags & GameFlag::COMPACT_MOVES > 0 {
+            MoveEncoding::Compact
+        } else {
+            MoveEncoding::Standard
+        };
 
-        let result = read_into_primitive!(reader, u8) as f32 / 2.0;
+        let mut moves = buffer;
+        let mut ply = 0;
 
-        let mut moves = Vec::new();
+        Self::read_moves(reader, &startpos, &castling, flags, &mut moves, &mut ply)
+            .map_err(|err| err.at_ply(ply))?;
 
-        let mut pos = startpos;
+        moves.truncate(ply);
+
+        Ok(MontyFormat {
+            startpos,
+            castling,
+            result,
+            moves,
+            visit_precision,
+            move_encoding,
+            metadata,
+        })
+    }
+
+    fn read_moves(
+        reader: &mut impl std::io::BufRead,
+        startpos: &Position,
+        castling: &Castling,
+        flags: u8,
+        moves: &mut Vec<SearchData>,
+        ply: &mut usize,
+    ) -> Result<(), MontyFormatError> {
+        let mut pos = *startpos;
+        let mut legal = Vec::new();
+        let compact = flags & GameFlag::COMPACT_MOVES > 0;
 
         loop {
-            let best_move = Move::from(read_into_primitive!(reader, u16));
+            // the best move itself, or its index into the sorted legal moves
+            let (stored_move, index, has_visits) = if compact {
+                match read_into_primitive!(reader, u8) {
+                    MoveTag::END => return Ok(()),
+                    MoveTag::NO_VISITS => (None, Some(read_into_primitive!(reader, u8)), false),
+                    MoveTag::VISITS => (None, Some(read_into_primitive!(reader, u8)), true),
+                    MoveTag::MOST_VISITED => (None, None, true),
+                    _ => return Err(unknown_move_tag()),
+                }
+            } else {
+                let best_move = Move::from(read_into_primitive!(reader, u16));
 
-            if best_move == Move::NULL {
-                break;
-            }
+                if best_move == Move::NULL {
+                    return Ok(());
+                }
+
+                (Some(best_move), None, false)
+            };
 
             let score = f32::from(read_into_primitive!(reader, u16)) / f32::from(u16::MAX);
 
-            let num_moves = read_into_primitive!(reader, u8);
+            let wdl = if flags & GameFlag::WDL > 0 {
+                Some(read_wdl(reader)?)
+            } else {
+                None
+            };
 
-            let visit_distribution = if num_moves == 0 {
+            let stats = if flags & GameFlag::STATS > 0 {
+                Some(SearchStats {
+                    nodes: read_varint(reader)?,
+                    avg_depth: read_into_primitive!(reader, u8),
+                    max_depth: read_into_primitive!(reader, u8),
+                    time_ms: read_varint(reader)?,
+                })
+            } else {
                 None
+            };
+
+            let stored_visits = if compact {
+                0
             } else {
-                let mut dist = Vec::with_capacity(usize::from(num_moves));
+                usize::from(read_into_primitive!(reader, u8))
+            };
 
-                pos.map_legal_moves(&castling, |mov| dist.push((mov, 0)));
-                dist.sort_by_key(|(mov, _)| u16::from(*mov));
+            sorted_legal_moves(&pos, castling, &mut legal);
 
-                assert_eq!(
-                    dist.len(),
-                    usize::from(num_moves),
-                    "{}\n{:?}",
-                    pos.as_fen(),
-                    castling.rook_files(),
-                );
+            if let Some(best_move) = stored_move {
+                if !legal.contains(&best_move) {
+                    return Err(MontyFormatError::IllegalMove {
+                        offset: 0,
+                        ply: *ply,
+                        mov: best_move,
+                        fen: pos.to_fen(castling, FenOptions::default()),
+                    });
+                }
+            }
+
+            let num_moves = if has_visits {
+                legal.len()
+            } else {
+                stored_visits
+            };
+
+            let spare = moves
+                .get_mut(*ply)
+                .and_then(|data| data.visit_distribution.take());
+
+            let visit_distribution = if num_moves == 0 {
+                None
+            } else {
+                if legal.len() != num_moves {
+                    return Err(MontyFormatError::VisitCountMismatch {
+                        offset: 0,
+                        ply: *ply,
+                        stored: num_moves,
+                        legal: legal.len(),
+                        fen: pos.to_fen(castling, FenOptions::default()),
+                    });
+                }
+
+                let mut dist = spare.unwrap_or_default();
+                dist.clear();
+                dist.extend(legal.iter().map(|&mov| (mov, 0)));
 
                 for entry in &mut dist {
-                    entry.1 = u32::from(read_into_primitive!(reader, u8));
+                    entry.1 = if flags & GameFlag::U16_VISITS > 0 {
+                        u32::from(read_into_primitive!(reader, u16))
+                    } else {
+                        u32::from(read_into_primitive!(reader, u8))
+                    };
                 }
 
                 Some(dist)
             };
 
-            moves.push(SearchData {
+            let best_move = match (stored_move, index, &visit_distribution) {
+                (Some(best_move), _, _) => Some(best_move),
+                (None, Some(index), _) => legal.get(usize::from(index)).copied(),
+                (None, None, Some(dist)) => {
+                    most_visited(dist.iter().map(|entry| entry.1)).map(|index| dist[index].0)
+                }
+                _ => None,
+            }
+            .ok_or_else(best_move_out_of_range)?;
+
+            let data = SearchData {
                 best_move,
                 score,
                 visit_distribution,
-            });
+                wdl,
+                stats,
+            };
+
+            if let Some(slot) = moves.get_mut(*ply) {
+                *slot = data;
+            } else {
+                moves.push(data);
+            }
+
+            *ply += 1;
 
-            pos.make(best_move, &castling);
+            pos.make(best_move, castling);
         }
+    }
 
-        Ok(MontyFormat {
-            startpos,
-            castling,
-            result,
-            moves,
-        })
+    pub fn interleave(
+        input_paths: &[String],
+        output_path: &str,
+        seed: u64,
+    ) -> Result<(), MontyFormatError> {
+        interleave::<Self>(input_paths, output_path, seed, false)
     }
 
-    pub fn interleave(input_paths: &[String], output_path: &str, seed: u64) -> std::io::Result<()> {
-        interleave::<Self>(input_paths, output_path, seed)
+    /// Interleaves headerless files written before the file header existed.
+    pub fn interleave_legacy(
+        input_paths: &[String],
+        output_path: &str,
+        seed: u64,
+    ) -> Result<(), MontyFormatError> {
+        interleave::<Self>(input_paths, output_path, seed, true)
     }
 }
 
 impl FastDeserialise for MontyFormat {
+    const KIND: FormatKind = FormatKind::Policy;
+
     fn deserialise_fast_into_buffer(
         reader: &mut impl std::io::BufRead,
         buffer: &mut Vec<u8>,
-    ) -> std::io::Result<()> {
+    ) -> Result<usize, MontyFormatError> {
         buffer.clear();
 
+        if reader.fill_buf()?.is_empty() {
+            return Err(MontyFormatError::Io(ErrorKind::UnexpectedEof.into()));
+        }
+
         for _ in 0..4 {
             let _ = read_primitive_into_vec!(reader, buffer, u64);
         }
@@ -239,7 +563,77 @@ impl FastDeserialise for MontyFormat {
             let _ = read_primitive_into_vec!(reader, buffer, u8);
         }
 
-        let _ = read_primitive_into_vec!(reader, buffer, u8);
+        let flags = read_primitive_into_vec!(reader, buffer, u8);
+        let visit_width = if flags & GameFlag::U16_VISITS > 0 {
+            2
+        } else {
+            1
+        };
+
+        if flags & GameFlag::METADATA > 0 {
+            let len = read_primitive_into_vec!(reader, buffer, u16);
+            let start = buffer.len();
+            buffer.resize(start + usize::from(len), 0);
+            reader.read_exact(&mut buffer[start..])?;
+        }
+
+        let mut plies = 0;
+
+        if flags & GameFlag::COMPACT_MOVES > 0 {
+            let (mut pos, castling, _, _) =
+                read_game_header(&mut &buffer[..43], !GameFlag::RESULT)?;
+            let mut legal = Vec::new();
+
+            loop {
+                let tag = read_primitive_into_vec!(reader, buffer, u8);
+
+                let mut index = match tag {
+                    MoveTag::END => break,
+                    MoveTag::NO_VISITS | MoveTag::VISITS => {
+                        Some(usize::from(read_primitive_into_vec!(reader, buffer, u8)))
+                    }
+                    MoveTag::MOST_VISITED => None,
+                    _ => return Err(unknown_move_tag()),
+                };
+
+                let _ = read_primitive_into_vec!(reader, buffer, u16);
+
+                if flags & GameFlag::WDL > 0 {
+                    let _ = read_primitive_into_vec!(reader, buffer, u32);
+                }
+
+                if flags & GameFlag::STATS > 0 {
+                    read_varint_into_vec(reader, buffer)?;
+                    let _ = read_primitive_into_vec!(reader, buffer, u16);
+                    read_varint_into_vec(reader, buffer)?;
+                }
+
+                sorted_legal_moves(&pos, &castling, &mut legal);
+
+                if tag != MoveTag::NO_VISITS {
+                    let mut visits = Vec::with_capacity(legal.len());
+
+                    for _ in 0..legal.len() {
+                        visits.push(if visit_width == 2 {
+                            u32::from(read_primitive_into_vec!(reader, buffer, u16))
+                        } else {
+                            u32::from(read_primitive_into_vec!(reader, buffer, u8))
+                        });
+                    }
+
+                    index = index.or(most_visited(visits.into_iter()));
+                }
+
+                let best_move = index
+                    .and_then(|index| legal.get(index))
+                    .ok_or_else(best_move_out_of_range)?;
+
+                pos.make(*best_move, &castling);
+                plies += 1;
+            }
+
+            return Ok(plies);
+        }
 
         loop {
             let best_move = Move::from(read_primitive_into_vec!(reader, buffer, u16));
@@ -250,19 +644,247 @@ impl FastDeserialise for MontyFormat {
 
             let _ = read_primitive_into_vec!(reader, buffer, u16);
 
+            if flags & GameFlag::WDL > 0 {
+                let _ = read_primitive_into_vec!(reader, buffer, u32);
+            }
+
+            if flags & GameFlag::STATS > 0 {
+                read_varint_into_vec(reader, buffer)?;
+                let _ = read_primitive_into_vec!(reader, buffer, u16);
+                read_varint_into_vec(reader, buffer)?;
+            }
+
             let num_moves = read_primitive_into_vec!(reader, buffer, u8);
 
-            if num_moves > 0 {
-                for _ in 0..num_moves {
-                    let _ = read_primitive_into_vec!(reader, buffer, u8);
-                }
-            };
+            for _ in 0..usize::from(num_moves) * visit_width {
+                let _ = read_primitive_into_vec!(reader, buffer, u8);
+            }
+
+            plies += 1;
         }
 
-        Ok(())
+        Ok(plies)
+    }
+}
+
+fn sorted_legal_moves(pos: &Position, castling: &Castling, legal: &mut Vec<Move>) {
+    legal.clear();
+    pos.map_legal_moves(castling, |mov| legal.push(mov));
+    legal.sort_by_key(|&mov| u16::from(mov));
+}
+
+/// Index of the first move with the most visits.
+fn most_visited(visits: impl Iterator<Item = u32>) -> Option<usize> {
+    let mut best = None;
+
+    for (index, visits) in visits.enumerate() {
+        if best.is_none_or(|(_, most)| visits > most) {
+            best = Some((index, visits));
+        }
+    }
+
+    best.map(|(index, _)| index)
+}
+
+fn unknown_move_tag() -> MontyFormatError {
+    MontyFormatError::InvalidEncoding {
+        offset: 0,
+        reason: "unknown move tag",
+    }
+}
+
+fn best_move_out_of_range() -> MontyFormatError {
+    MontyFormatError::InvalidEncoding {
+        offset: 0,
+        reason: "best move index out of range",
     }
 }
 
+pub(crate) fn write_game_header(
+    writer: &mut impl Write,
+    startpos: &Position,
+    castling: &Castling,
+    result: f32,
+    flags: u8,
+) -> Result<(), MontyFormatError> {
+    if ![0.0, 0.5, 1.0].contains(&result) {
+        return Err(MontyFormatError::InvalidResult { offset: 0, result });
+    }
+
+    let compressed = CompressedChessBoard::from(*startpos);
+
+    for bb in compressed.bbs {
+        writer.write_all(&bb.to_le_bytes())?;
+    }
+
+    writer.write_all(&compressed.stm.to_le_bytes())?;
+    writer.write_all(&compressed.enp_sq.to_le_bytes())?;
+    writer.write_all(&compressed.rights.to_le_bytes())?;
+    writer.write_all(&compressed.halfm.to_le_bytes())?;
+    writer.write_all(&compressed.fullm.to_le_bytes())?;
+
+    for side in castling.rook_files() {
+        for rook in side {
+            writer.write_all(&rook.to_le_bytes())?;
+        }
+    }
+
+    let result = (result * 2.0) as u8 | flags;
+    writer.write_all(&result.to_le_bytes())?;
+
+    Ok(())
+}
+
+pub(crate) fn write_wdl(
+    writer: &mut impl Write,
+    wdl: Option<Wdl>,
+    ply: usize,
+) -> Result<(), MontyFormatError> {
+    let wdl = wdl.ok_or(MontyFormatError::MissingPayload {
+        ply,
+        payload: "WDL",
+    })?;
+
+    if !wdl.is_valid() {
+        return Err(MontyFormatError::InvalidWdl { ply, wdl });
+    }
+
+    for prob in wdl.to_raw() {
+        writer.write_all(&prob.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn read_wdl(reader: &mut impl std::io::BufRead) -> Result<Wdl, MontyFormatError> {
+    let win = read_into_primitive!(reader, u16);
+    let draw = read_into_primitive!(reader, u16);
+    Ok(Wdl::from_raw([win, draw]))
+}
+
+// LEB128: 7 bits per byte, high bit set on all but the last byte
+pub(crate) fn write_varint(
+    writer: &mut impl Write,
+    mut value: u64,
+) -> Result<(), MontyFormatError> {
+    while value >= 0x80 {
+        writer.write_all(&[value as u8 | 0x80])?;
+        value >>= 7;
+    }
+
+    writer.write_all(&[value as u8])?;
+    Ok(())
+}
+
+pub(crate) fn read_varint(reader: &mut impl std::io::BufRead) -> Result<u64, MontyFormatError> {
+    let mut value = 0;
+
+    for shift in (0..64).step_by(7) {
+        let byte = read_into_primitive!(reader, u8);
+        value |= u64::from(byte & 0x7F) << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(MontyFormatError::InvalidEncoding {
+        offset: 0,
+        reason: "varint longer than 64 bits",
+    })
+}
+
+fn read_varint_into_vec(
+    reader: &mut impl std::io::BufRead,
+    buffer: &mut Vec<u8>,
+) -> Result<(), MontyFormatError> {
+    while read_primitive_into_vec!(reader, buffer, u8) & 0x80 > 0 {}
+    Ok(())
+}
+
+/// Reads everything before the moves, returning the per-game flags found
+/// alongside the result. Flags outside `known_flags` are an error.
+pub(crate) fn read_game_header(
+    reader: &mut impl std::io::BufRead,
+    known_flags: u8,
+) -> Result<(Position, Castling, f32, u8), MontyFormatError> {
+    if reader.fill_buf()?.is_empty() {
+        return Err(MontyFormatError::Io(ErrorKind::UnexpectedEof.into()));
+    }
+
+    let mut bbs = [0u64; 4];
+    for bb in &mut bbs {
+        *bb = read_into_primitive!(reader, u64);
+    }
+
+    let stm = read_into_primitive!(reader, u8);
+    let enp_sq = read_into_primitive!(reader, u8);
+    let rights = read_into_primitive!(reader, u8);
+    let halfm = read_into_primitive!(reader, u8);
+    let fullm = read_into_primitive!(reader, u16);
+
+    let compressed = CompressedChessBoard {
+        bbs,
+        stm,
+        enp_sq,
+        rights,
+        halfm,
+        fullm,
+    };
+
+    let invalid = |reason| MontyFormatError::InvalidPosition { offset: 0, reason };
+
+    if enp_sq >= 64 {
+        return Err(invalid("en passant square out of range"));
+    }
+
+    if rights > 15 {
+        return Err(invalid("castling rights out of range"));
+    }
+
+    let startpos = Position::from(compressed);
+
+    for side in [Side::WHITE, Side::BLACK] {
+        if (startpos.piece(side) & startpos.piece(Piece::KING)).count_ones() != 1 {
+            return Err(invalid("side does not have exactly one king"));
+        }
+    }
+
+    let mut rook_files = [[0; 2]; 2];
+    for side in &mut rook_files {
+        for rook in side {
+            *rook = read_into_primitive!(reader, u8);
+
+            if *rook > 7 {
+                return Err(invalid("rook file out of range"));
+            }
+        }
+    }
+
+    let castling = Castling::from_raw(&startpos, rook_files);
+
+    startpos
+        .validate(&castling)
+        .map_err(|err| invalid(err.reason()))?;
+
+    let byte = read_into_primitive!(reader, u8);
+    let result = byte & GameFlag::RESULT;
+    let flags = byte & !GameFlag::RESULT;
+
+    if result > 2 {
+        return Err(MontyFormatError::InvalidResult {
+            offset: 0,
+            result: f32::from(result) / 2.0,
+        });
+    }
+
+    if flags & !known_flags > 0 {
+        return Err(MontyFormatError::UnsupportedGameFlags { offset: 0, flags });
+    }
+
+    Ok((startpos, castling, f32::from(result) / 2.0, flags))
+}
+
 #[derive(Clone, Copy)]
 pub struct CompressedChessBoard {
     pub bbs: [u64; 4],
@@ -328,3 +950,180 @@ impl From<CompressedChessBoard> for Position {
         )
     }
 }
+
+/// A single position packed as bulletformat's 32-byte `ChessBoard`: the
+/// board is flipped so the side to move is white, and each occupied square,
+/// in order, gets a nibble of colour (bit 3) and piece (bits 0-2).
+#[derive(Clone, Copy)]
+pub struct BulletChessBoard {
+    pub occ: u64,
+    pub pcs: [u8; 16],
+    pub score: i16,
+    pub result: u8,
+    pub ksq: u8,
+    pub opp_ksq: u8,
+    pub extra: [u8; 3],
+}
+
+impl BulletChessBoard {
+    pub const SIZE: usize = 32;
+
+    /// `score` is relative to the side to move and `result` to white.
+    /// Returns `None` for positions with more than 32 pieces.
+    pub fn new(board: &Position, score: i16, result: f32) -> Option<Self> {
+        let mut bbs = board.bbs();
+        let mut result = result;
+
+        if board.stm() == Side::BLACK {
+            for bb in bbs.iter_mut() {
+                *bb = bb.swap_bytes();
+            }
+
+            bbs.swap(0, 1);
+            result = 1.0 - result;
+        }
+
+        let occ = bbs[0] | bbs[1];
+
+        if occ.count_ones() > 32 {
+            return None;
+        }
+
+        let mut pcs = [0; 16];
+        let mut idx = 0;
+
+        bitloop!(|occ, sq| {
+            let bit = 1 << sq;
+            let colour = u8::from(bit & bbs[1] > 0) << 3;
+            let piece = (2..8).position(|pc| bit & bbs[pc] > 0).unwrap_or(0) as u8;
+
+            pcs[idx / 2] |= (colour | piece) << (4 * (idx & 1));
+            idx += 1;
+        });
+
+        Some(Self {
+            occ,
+            pcs,
+            score,
+            result: (2.0 * result) as u8,
+            ksq: (bbs[0] & bbs[7]).trailing_zeros() as u8,
+            opp_ksq: (bbs[1] & bbs[7]).trailing_zeros() as u8 ^ 56,
+            extra: [0; 3],
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+
+        bytes[..8].copy_from_slice(&self.occ.to_le_bytes());
+        bytes[8..24].copy_from_slice(&self.pcs);
+        bytes[24..26].copy_from_slice(&self.score.to_le_bytes());
+        bytes[26] = self.result;
+        bytes[27] = self.ksq;
+        bytes[28] = self.opp_ksq;
+        bytes[29..].copy_from_slice(&self.extra);
+
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::STARTPOS;
+
+    /// A game whose best move is the most visited on even plies only, so
+    /// both `MoveTag::MOST_VISITED` and an explicit index get written.
+    fn compact_game(plies: usize, with_visits: bool, precision: VisitPrecision) -> MontyFormat {
+        let mut castling = Castling::default();
+        let startpos = Position::parse_fen(STARTPOS, &mut castling);
+
+        let mut game = MontyFormat::new(startpos, castling);
+        game.move_encoding = MoveEncoding::Compact;
+        game.visit_precision = precision;
+
+        let mut pos = startpos;
+        let mut legal = Vec::new();
+
+        for ply in 0..plies {
+            sorted_legal_moves(&pos, &castling, &mut legal);
+
+            let best = (ply * 7) % legal.len();
+            let most = if ply % 2 == 0 {
+                best
+            } else {
+                (best + 1) % legal.len()
+            };
+
+            let dist = legal
+                .iter()
+                .enumerate()
+                .map(|(i, &mov)| (mov, if i == most { 1000 } else { 10 + i as u32 }));
+
+            let dist = with_visits.then(|| dist.collect());
+            game.push(SearchData::new(legal[best], 0.5, dist));
+            pos.make(legal[best], &castling);
+        }
+
+        game.result = 0.5;
+        game
+    }
+
+    #[test]
+    fn compact_fast_path_agrees_with_decoder() {
+        for (with_visits, precision) in [
+            (false, VisitPrecision::U8),
+            (true, VisitPrecision::U8),
+            (true, VisitPrecision::U16),
+        ] {
+            let game = compact_game(40, with_visits, precision);
+
+            let mut bytes = Vec::new();
+            game.serialise_into_buffer(&mut bytes).unwrap();
+            let len = bytes.len();
+
+            // a second game after it, which the fast path must not eat into
+            let mut next = Vec::new();
+            compact_game(5, !with_visits, precision)
+                .serialise_into_buffer(&mut next)
+                .unwrap();
+            bytes.extend_from_slice(&next);
+
+            let mut reader = &bytes[..];
+            let mut buffer = Vec::new();
+            let plies = MontyFormat::deserialise_fast_into_buffer(&mut reader, &mut buffer);
+
+            assert_eq!(plies.unwrap(), game.moves.len());
+            assert_eq!(buffer, bytes[..len]);
+            assert_eq!(reader, &next[..]);
+
+            let read = MontyFormat::deserialise_from(&mut &buffer[..]).unwrap();
+            assert_eq!(read.move_encoding, MoveEncoding::Compact);
+            assert_eq!(read.moves.len(), game.moves.len());
+
+            for (read, data) in read.moves.iter().zip(&game.moves) {
+                assert_eq!(read.best_move, data.best_move);
+                assert_eq!(
+                    read.visit_distribution.is_some(),
+                    data.visit_distribution.is_some()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn compact_omits_the_most_visited_move() {
+        // the tag of ply `n` directly follows a game of its first `n` plies
+        let bytes = |plies| {
+            let mut bytes = Vec::new();
+            compact_game(plies, true, VisitPrecision::U8)
+                .serialise_into_buffer(&mut bytes)
+                .unwrap();
+            bytes
+        };
+
+        let (even, odd) = (bytes(38), bytes(39));
+        assert_eq!(odd[even.len() - 1], MoveTag::MOST_VISITED);
+        assert_eq!(bytes(40)[odd.len() - 1], MoveTag::VISITS);
+    }
+}
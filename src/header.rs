@@ -0,0 +1,123 @@
+use std::io::{ErrorKind, Read, Write};
+
+use crate::MontyFormatError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormatKind {
+    Policy,
+    Value,
+}
+
+impl std::fmt::Display for FormatKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Policy => write!(f, "MontyFormat"),
+            Self::Value => write!(f, "MontyValueFormat"),
+        }
+    }
+}
+
+/// Fixed-size header at the start of a binpack:
+/// magic (4 bytes), format kind (1), reserved (1), version (2), flags (4).
+///
+/// Version 2 allows per-game flags in the high bits of each result byte.
+/// With `COMPRESSED` set, the games that follow are stored in compressed
+/// blocks, see `BlockReader`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileHeader {
+    pub kind: FormatKind,
+    pub version: u16,
+    pub flags: u32,
+}
+
+impl FileHeader {
+    pub const MAGIC: [u8; 4] = *b"MNTY";
+    pub const SIZE: u64 = 12;
+    pub const VERSION: u16 = 2;
+    pub const COMPRESSED: u32 = 1;
+    pub const KNOWN_FLAGS: u32 = Self::COMPRESSED;
+
+    pub fn new(kind: FormatKind) -> Self {
+        Self {
+            kind,
+            version: Self::VERSION,
+            flags: 0,
+        }
+    }
+
+    /// Header for a file whose games are written in compressed blocks.
+    pub fn compressed(kind: FormatKind) -> Self {
+        Self {
+            flags: Self::COMPRESSED,
+            ..Self::new(kind)
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.flags & Self::COMPRESSED != 0
+    }
+
+    pub fn write_into(&self, writer: &mut impl Write) -> Result<(), MontyFormatError> {
+        let kind: u8 = match self.kind {
+            FormatKind::Policy => 0,
+            FormatKind::Value => 1,
+        };
+
+        writer.write_all(&Self::MAGIC)?;
+        writer.write_all(&[kind, 0])?;
+        writer.write_all(&self.version.to_le_bytes())?;
+        writer.write_all(&self.flags.to_le_bytes())?;
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> Result<Self, MontyFormatError> {
+        let mut buf = [0; Self::SIZE as usize];
+        reader
+            .read_exact(&mut buf)
+            .map_err(|err| match err.kind() {
+                ErrorKind::UnexpectedEof => MontyFormatError::InvalidHeader("file too short"),
+                _ => MontyFormatError::Io(err),
+            })?;
+
+        if buf[..4] != Self::MAGIC {
+            return Err(MontyFormatError::InvalidHeader("missing magic bytes"));
+        }
+
+        let kind = match buf[4] {
+            0 => FormatKind::Policy,
+            1 => FormatKind::Value,
+            _ => return Err(MontyFormatError::InvalidHeader("unknown format kind")),
+        };
+
+        let version = u16::from_le_bytes([buf[6], buf[7]]);
+        let flags = u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]);
+
+        if version == 0 || version > Self::VERSION {
+            return Err(MontyFormatError::UnsupportedVersion(version));
+        }
+
+        if flags & !Self::KNOWN_FLAGS != 0 {
+            return Err(MontyFormatError::UnsupportedFlags(flags));
+        }
+
+        Ok(Self {
+            kind,
+            version,
+            flags,
+        })
+    }
+
+    /// Reads a header and checks that it describes a file of the given kind.
+    pub fn expect(reader: &mut impl Read, kind: FormatKind) -> Result<Self, MontyFormatError> {
+        let header = Self::read_from(reader)?;
+
+        if header.kind != kind {
+            return Err(MontyFormatError::FormatMismatch {
+                expected: kind,
+                found: header.kind,
+            });
+        }
+
+        Ok(header)
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write},
+};
+
+use crate::{read_into_primitive, Deserialise, FileHeader, FormatKind, MontyFormatError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub offset: u64,
+    pub plies: u32,
+}
+
+/// Byte offset and ply count of every game in a binpack, stored in a sidecar
+/// file: magic (4 bytes), format kind (1), version (1), reserved (2), length
+/// of the indexed file (8), game count (8), followed by an (offset: u64,
+/// plies: u32) pair per game.
+pub struct GameIndex {
+    kind: FormatKind,
+    data_len: u64,
+    entries: Vec<IndexEntry>,
+}
+
+impl GameIndex {
+    pub const MAGIC: [u8; 4] = *b"MIDX";
+    pub const VERSION: u8 = 1;
+
+    pub fn build<T: Deserialise>(
+        reader: &mut impl BufRead,
+        legacy: bool,
+    ) -> Result<Self, MontyFormatError> {
+        let mut offset = 0;
+
+        if !legacy {
+            // offsets into compressed blocks cannot be seeked to
+            if FileHeader::expect(reader, T::KIND)?.is_compressed() {
+                return Err(MontyFormatError::InvalidHeader(
+                    "cannot index a compressed file",
+                ));
+            }
+
+            offset = FileHeader::SIZE;
+        }
+
+        let mut entries = Vec::new();
+        let mut buffer = Vec::new();
+
+        loop {
+            let plies = match T::deserialise_fast_into_buffer(reader, &mut buffer) {
+                Ok(plies) => plies,
+                Err(MontyFormatError::Io(err)) if err.kind() == ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err.at_offset(offset)),
+            };
+
+            entries.push(IndexEntry {
+                offset,
+                plies: plies as u32,
+            });
+
+            offset += buffer.len() as u64;
+        }
+
+        Ok(Self {
+            kind: T::KIND,
+            data_len: offset,
+            entries,
+        })
+    }
+
+    pub fn build_for_file<T: Deserialise>(
+        path: &str,
+        legacy: bool,
+    ) -> Result<Self, MontyFormatError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        Self::build::<T>(&mut reader, legacy)
+    }
+
+    pub fn sidecar_path(path: &str) -> String {
+        format!("{path}.idx")
+    }
+
+    pub fn kind(&self) -> FormatKind {
+        self.kind
+    }
+
+    /// Length in bytes of the file the index was built from.
+    pub fn data_len(&self) -> u64 {
+        self.data_len
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, game: usize) -> Option<IndexEntry> {
+        self.entries.get(game).copied()
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    pub fn total_plies(&self) -> u64 {
+        self.entries
+            .iter()
+            .map(|entry| u64::from(entry.plies))
+            .sum()
+    }
+
+    pub fn write_into(&self, writer: &mut impl Write) -> Result<(), MontyFormatError> {
+        let kind: u8 = match self.kind {
+            FormatKind::Policy => 0,
+            FormatKind::Value => 1,
+        };
+
+        writer.write_all(&Self::MAGIC)?;
+        writer.write_all(&[kind, Self::VERSION, 0, 0])?;
+        writer.write_all(&self.data_len.to_le_bytes())?;
+        writer.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+
+        for entry in &self.entries {
+            writer.write_all(&entry.offset.to_le_bytes())?;
+            writer.write_all(&entry.plies.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> Result<Self, MontyFormatError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+
+        if magic != Self::MAGIC {
+            return Err(MontyFormatError::InvalidHeader("missing index magic bytes"));
+        }
+
+        let mut kind = [0; 4];
+        reader.read_exact(&mut kind)?;
+
+        if kind[1] != Self::VERSION {
+            return Err(MontyFormatError::InvalidHeader(
+                "unsupported index version, rebuild the index",
+            ));
+        }
+
+        let kind = match kind[0] {
+            0 => FormatKind::Policy,
+            1 => FormatKind::Value,
+            _ => return Err(MontyFormatError::InvalidHeader("unknown format kind")),
+        };
+
+        let data_len = read_into_primitive!(reader, u64);
+        let len = read_into_primitive!(reader, u64);
+
+        // the count is untrusted, so only reserve space for a sane amount up
+        // front and let a short file fail on reading
+        let mut entries = Vec::with_capacity(len.min(1 << 20) as usize);
+
+        for _ in 0..len {
+            let offset = read_into_primitive!(reader, u64);
+            let plies = read_into_primitive!(reader, u32);
+            entries.push(IndexEntry { offset, plies });
+        }
+
+        Ok(Self {
+            kind,
+            data_len,
+            entries,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), MontyFormatError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_into(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self, MontyFormatError> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+/// Reads individual games by number, seeking via a `GameIndex`.
+pub struct IndexedReader<R, T> {
+    reader: R,
+    index: GameIndex,
+    spare: Option<T>,
+}
+
+impl<R: BufRead + Seek, T: Deserialise> IndexedReader<R, T> {
+    /// Fails if the index is for the other format, or was built from a file
+    /// of a different length to `reader`.
+    pub fn new(mut reader: R, index: GameIndex) -> Result<Self, MontyFormatError> {
+        if index.kind != T::KIND {
+            return Err(MontyFormatError::FormatMismatch {
+                expected: T::KIND,
+                found: index.kind,
+            });
+        }
+
+        if reader.seek(SeekFrom::End(0))? != index.data_len {
+            return Err(MontyFormatError::InvalidHeader(
+                "index was built from a different file",
+            ));
+        }
+
+        Ok(Self {
+            reader,
+            index,
+            spare: None,
+        })
+    }
+
+    pub fn index(&self) -> &GameIndex {
+        &self.index
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Returns `None` if `game` is past the end of the index.
+    pub fn read_game(&mut self, game: usize) -> Option<Result<T, MontyFormatError>> {
+        let entry = self.index.get(game)?;
+
+        Some(
+            self.read_at(entry.offset)
+                .map_err(|err| err.at_offset(entry.offset)),
+        )
+    }
+
+    fn read_at(&mut self, offset: u64) -> Result<T, MontyFormatError> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+        T::deserialise_reusing(&mut self.reader, self.spare.take())
+    }
+
+    /// Hands a game back so its allocations are reused for the next read.
+    pub fn recycle(&mut self, game: T) {
+        self.spare = Some(game);
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
@@ -3,6 +3,8 @@ use std::{
     io::{BufReader, BufWriter, Write},
 };
 
+use crate::{compress, FileHeader, FormatKind, MontyFormatError};
+
 struct RandU64(u64);
 
 impl RandU64 {
@@ -15,36 +17,69 @@ impl RandU64 {
 }
 
 pub trait FastDeserialise {
+    const KIND: FormatKind;
+
     fn deserialise_fast_into_buffer(
         reader: &mut impl std::io::BufRead,
         buffer: &mut Vec<u8>,
-    ) -> std::io::Result<()>;
+    ) -> Result<usize, MontyFormatError>;
 }
 
+/// Shuffles the games of several files together into one. Compressed files
+/// are shuffled a whole block at a time, without decompressing them, so
+/// inputs must either all be compressed or all be uncompressed.
 pub fn interleave<T: FastDeserialise>(
     input_paths: &[String],
     output_path: &str,
     seed: u64,
-) -> std::io::Result<()> {
+    legacy: bool,
+) -> Result<(), MontyFormatError> {
     println!("Writing to {:#?}", output_path);
     println!("Reading from:\n{:#?}", input_paths);
     let mut streams = Vec::new();
     let mut total = 0;
-
-    let target = File::create(output_path)?;
-    let mut writer = BufWriter::new(target);
+    let mut flags = 0;
+    let mut compressed = None;
 
     for path in input_paths {
         let file = File::open(path)?;
 
-        let count = file.metadata()?.len();
+        let mut count = file.metadata()?.len();
+        let mut offset = 0;
+        let mut reader = BufReader::new(file);
+
+        if !legacy && count > 0 {
+            let header = FileHeader::expect(&mut reader, T::KIND)?;
+
+            if *compressed.get_or_insert(header.is_compressed()) != header.is_compressed() {
+                return Err(MontyFormatError::InvalidHeader(
+                    "cannot interleave compressed and uncompressed files",
+                ));
+            }
+
+            flags |= header.flags;
+            count -= FileHeader::SIZE;
+            offset = FileHeader::SIZE;
+        }
 
         if count > 0 {
-            streams.push((count, BufReader::new(file)));
+            streams.push((count, offset, reader));
             total += count;
         }
     }
 
+    let target = File::create(output_path)?;
+    let mut writer = BufWriter::new(target);
+
+    if !legacy {
+        let header = FileHeader {
+            flags,
+            ..FileHeader::new(T::KIND)
+        };
+
+        header.write_into(&mut writer)?;
+    }
+
     let mut remaining = total;
     let mut rng = RandU64(seed);
 
@@ -61,15 +96,26 @@ pub fn interleave<T: FastDeserialise>(
             idx += 1;
         }
 
-        let (count, reader) = &mut streams[idx];
-
-        T::deserialise_fast_into_buffer(reader, &mut buffer)?;
+        let (count, offset, reader) = &mut streams[idx];
+
+        if compressed == Some(true) {
+            if !compress::read_raw_block(reader, &mut buffer)? {
+                return Err(MontyFormatError::Truncated {
+                    offset: *offset,
+                    ply: None,
+                });
+            }
+        } else {
+            T::deserialise_fast_into_buffer(reader, &mut buffer)
+                .map_err(|err| err.at_offset(*offset))?;
+        }
         writer.write_all(&buffer)?;
 
         let size = buffer.len() as u64;
 
         remaining -= size;
         *count -= size;
+        *offset += size;
         if *count == 0 {
             streams.swap_remove(idx);
         }
@@ -85,5 +131,7 @@ pub fn interleave<T: FastDeserialise>(
         }
     }
 
+    writer.flush()?;
+
     Ok(())
 }
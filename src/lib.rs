@@ -1,11 +1,39 @@
+mod bullet;
 pub mod chess;
+mod compress;
+mod convert;
+mod error;
 mod format;
+mod header;
+mod index;
 mod interleave;
+mod metadata;
+mod pgn;
+mod reader;
+mod replay;
+mod text;
+mod validate;
 mod value;
+mod writer;
 
-pub use format::{MontyFormat, SearchData};
+pub use bullet::export_bullet;
+pub use compress::BlockReader;
+pub use convert::{convert_to_value, ConvertOptions};
+pub use error::MontyFormatError;
+pub use format::{
+    BulletChessBoard, MontyFormat, MoveEncoding, SearchData, SearchStats, VisitPrecision, Wdl,
+};
+pub use header::{FileHeader, FormatKind};
+pub use index::{GameIndex, IndexEntry, IndexedReader};
 pub use interleave::FastDeserialise;
+pub use metadata::{GameMetadata, Termination};
+pub use pgn::{PgnGame, PgnReader, ToPgn};
+pub use reader::{Deserialise, GameReader, MontyFormatReader, MontyValueFormatReader};
+pub use replay::{Replay, ScoredPositions};
+pub use text::{export_text, TextReader};
+pub use validate::{validate, Issue, Validate, ValidationReport};
 pub use value::{MontyValueFormat, SearchResult};
+pub use writer::{GameWriter, MontyFormatWriter, MontyValueFormatWriter, Serialise};
 
 macro_rules! init {
     (|$sq:ident, $size:literal | $($rest:tt)+) => {{
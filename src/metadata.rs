@@ -0,0 +1,146 @@
+use std::io::{Read, Write};
+
+use crate::{
+    format::{read_varint, write_varint},
+    read_into_primitive, MontyFormatError,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Termination {
+    #[default]
+    Unknown,
+    Checkmate,
+    Stalemate,
+    FiftyMove,
+    Repetition,
+    InsufficientMaterial,
+    Adjudication,
+    MoveLimit,
+}
+
+impl Termination {
+    const ALL: [Self; 8] = [
+        Self::Unknown,
+        Self::Checkmate,
+        Self::Stalemate,
+        Self::FiftyMove,
+        Self::Repetition,
+        Self::InsufficientMaterial,
+        Self::Adjudication,
+        Self::MoveLimit,
+    ];
+
+    fn from_raw(raw: u8) -> Self {
+        Self::ALL.get(usize::from(raw)).copied().unwrap_or_default()
+    }
+
+    fn to_raw(self) -> u8 {
+        Self::ALL.iter().position(|&x| x == self).unwrap() as u8
+    }
+}
+
+/// Describes how a game was produced. Written as a length-prefixed block
+/// after the result byte, so readers can skip it without parsing and fields
+/// appended in future remain readable by older versions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GameMetadata {
+    pub engine: String,
+    pub network_hash: u64,
+    pub node_limit: Option<u64>,
+    pub time_limit_ms: Option<u64>,
+    pub opening_id: Option<u64>,
+    pub termination: Termination,
+    pub seed: u64,
+}
+
+impl GameMetadata {
+    const NODE_LIMIT: u8 = 1;
+    const TIME_LIMIT: u8 = 2;
+    const OPENING_ID: u8 = 4;
+
+    pub(crate) fn write_into(&self, writer: &mut impl Write) -> Result<(), MontyFormatError> {
+        let mut block = Vec::new();
+
+        let present = [
+            (self.node_limit, Self::NODE_LIMIT),
+            (self.time_limit_ms, Self::TIME_LIMIT),
+            (self.opening_id, Self::OPENING_ID),
+        ];
+
+        let bits = present
+            .iter()
+            .filter(|(field, _)| field.is_some())
+            .fold(0, |bits, (_, bit)| bits | bit);
+
+        block.write_all(&[bits, self.termination.to_raw()])?;
+        block.write_all(&self.network_hash.to_le_bytes())?;
+        block.write_all(&self.seed.to_le_bytes())?;
+
+        write_varint(&mut block, self.engine.len() as u64)?;
+        block.write_all(self.engine.as_bytes())?;
+
+        for value in present.iter().filter_map(|(field, _)| *field) {
+            write_varint(&mut block, value)?;
+        }
+
+        let len = u16::try_from(block.len()).map_err(|_| MontyFormatError::InvalidEncoding {
+            offset: 0,
+            reason: "metadata block too large",
+        })?;
+
+        writer.write_all(&len.to_le_bytes())?;
+        writer.write_all(&block)?;
+        Ok(())
+    }
+
+    pub(crate) fn read_from(reader: &mut impl std::io::BufRead) -> Result<Self, MontyFormatError> {
+        let len = read_into_primitive!(reader, u16);
+        let mut block = vec![0; usize::from(len)];
+        reader.read_exact(&mut block)?;
+
+        Self::parse(&mut &block[..]).map_err(|err| match err {
+            MontyFormatError::Truncated { .. } => MontyFormatError::InvalidEncoding {
+                offset: 0,
+                reason: "metadata block truncated",
+            },
+            err => err,
+        })
+    }
+
+    fn parse(block: &mut &[u8]) -> Result<Self, MontyFormatError> {
+        let bits = read_into_primitive!(block, u8);
+        let termination = Termination::from_raw(read_into_primitive!(block, u8));
+        let network_hash = read_into_primitive!(block, u64);
+        let seed = read_into_primitive!(block, u64);
+
+        let len = read_varint(block)? as usize;
+        if len > block.len() {
+            return Err(MontyFormatError::Truncated {
+                offset: 0,
+                ply: None,
+            });
+        }
+
+        let (engine, rest) = block.split_at(len);
+        let engine = String::from_utf8_lossy(engine).into_owned();
+        *block = rest;
+
+        let mut optional = |bit| -> Result<Option<u64>, MontyFormatError> {
+            if bits & bit > 0 {
+                Ok(Some(read_varint(block)?))
+            } else {
+                Ok(None)
+            }
+        };
+
+        Ok(Self {
+            engine,
+            network_hash,
+            node_limit: optional(Self::NODE_LIMIT)?,
+            time_limit_ms: optional(Self::TIME_LIMIT)?,
+            opening_id: optional(Self::OPENING_ID)?,
+            termination,
+            seed,
+        })
+    }
+}
@@ -0,0 +1,545 @@
+use std::io::BufRead;
+
+use crate::{
+    chess::{
+        Castling, CastlingNotation, FenError, FenOptions, Move, Position, Right, Side, STARTPOS,
+    },
+    value::win_prob_to_cp,
+    MontyFormat, MontyFormatError, MontyValueFormat, SearchData,
+};
+
+const MAX_LINE_LEN: usize = 80;
+const TOP_VISITS: usize = 3;
+
+pub trait ToPgn {
+    /// Renders the game as PGN, with each move's score as a pawn eval from
+    /// the mover's point of view. `tags` override entries of the seven tag
+    /// roster or are appended after it.
+    fn to_pgn(&self, tags: &[(&str, &str)]) -> String;
+}
+
+impl ToPgn for MontyFormat {
+    fn to_pgn(&self, tags: &[(&str, &str)]) -> String {
+        let mut roster = Vec::new();
+
+        if let Some(metadata) = &self.metadata {
+            if !metadata.engine.is_empty() {
+                roster.push(("White", metadata.engine.as_str()));
+                roster.push(("Black", metadata.engine.as_str()));
+            }
+        }
+
+        roster.extend_from_slice(tags);
+
+        let mut replay = self.replay();
+        let mut moves = Vec::with_capacity(self.moves.len());
+
+        while let Some((pos, data, _, _)) = replay.next() {
+            let mut comment = format_eval(f32::from(win_prob_to_cp(data.score)));
+
+            if let Some(dist) = &data.visit_distribution {
+                let total = dist.iter().map(|&(_, visits)| visits as f32).sum::<f32>();
+
+                if total > 0.0 {
+                    let mut top = dist.clone();
+                    top.sort_by_key(|&(_, visits)| std::cmp::Reverse(visits));
+
+                    for (mov, visits) in top.into_iter().take(TOP_VISITS) {
+                        let san = pos.san(mov, replay.castling());
+                        let share = 100.0 * visits as f32 / total;
+                        comment += &format!(" {san} {share:.1}%");
+                    }
+                }
+            }
+
+            moves.push((data.best_move, comment));
+        }
+
+        write_pgn(&self.startpos, &self.castling, self.result, &roster, moves)
+    }
+}
+
+impl ToPgn for MontyValueFormat {
+    fn to_pgn(&self, tags: &[(&str, &str)]) -> String {
+        let moves = self.replay().map(|(pos, data, _, _)| {
+            let score = f32::from(data.score);
+            let score = if pos.stm() == Side::WHITE {
+                score
+            } else {
+                -score
+            };
+            (data.best_move, format_eval(score))
+        });
+
+        write_pgn(&self.startpos, &self.castling, self.result, tags, moves)
+    }
+}
+
+fn write_pgn(
+    startpos: &Position,
+    castling: &Castling,
+    result: f32,
+    tags: &[(&str, &str)],
+    moves: impl IntoIterator<Item = (Move, String)>,
+) -> String {
+    let result = result_str(result);
+    let chess960 = is_chess960(startpos, castling);
+    let fen = startpos.to_fen(castling, FenOptions::default());
+
+    let mut all_tags = vec![
+        ("Event", "?"),
+        ("Site", "?"),
+        ("Date", "????.??.??"),
+        ("Round", "?"),
+        ("White", "?"),
+        ("Black", "?"),
+        ("Result", result),
+    ];
+
+    for &(name, value) in tags {
+        match all_tags.iter_mut().find(|(tag, _)| *tag == name) {
+            Some(tag) => tag.1 = value,
+            None => all_tags.push((name, value)),
+        }
+    }
+
+    if chess960 {
+        all_tags.push(("Variant", "Chess960"));
+    }
+
+    if chess960 || fen != STARTPOS {
+        all_tags.push(("SetUp", "1"));
+        all_tags.push(("FEN", &fen));
+    }
+
+    let mut pgn = String::new();
+    for (name, value) in all_tags {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+        pgn += &format!("[{name} \"{value}\"]\n");
+    }
+    pgn.push('\n');
+
+    let mut tokens = Vec::new();
+    let mut pos = *startpos;
+    let mut first = true;
+
+    for (mov, comment) in moves {
+        if pos.stm() == Side::WHITE {
+            tokens.push(format!("{}.", pos.fullm()));
+        } else if first {
+            tokens.push(format!("{}...", pos.fullm()));
+        }
+
+        tokens.push(pos.san(mov, castling));
+        tokens.push(format!("{{{comment}}}"));
+
+        pos.make(mov, castling);
+        first = false;
+    }
+
+    tokens.push(result.to_string());
+
+    let mut line_len = 0;
+    for token in tokens {
+        if line_len > 0 && line_len + 1 + token.len() > MAX_LINE_LEN {
+            pgn.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            pgn.push(' ');
+            line_len += 1;
+        }
+
+        line_len += token.len();
+        pgn += &token;
+    }
+
+    pgn.push('\n');
+    pgn
+}
+
+/// Writes a centipawn score in pawns, the usual unit of PGN eval comments.
+fn format_eval(score: f32) -> String {
+    format!("{:+.2}", score.clamp(-9999.0, 9999.0).round() / 100.0 + 0.0)
+}
+
+fn result_str(result: f32) -> &'static str {
+    if result == 1.0 {
+        "1-0"
+    } else if result == 0.0 {
+        "0-1"
+    } else if result == 0.5 {
+        "1/2-1/2"
+    } else {
+        "*"
+    }
+}
+
+fn is_chess960(pos: &Position, castling: &Castling) -> bool {
+    if castling.is_chess960() {
+        return true;
+    }
+
+    let rights = [Right::WQS | Right::WKS, Right::BQS | Right::BKS];
+
+    (0..2).any(|side| {
+        pos.rights() & rights[side] > 0
+            && (pos.king_sq(side) % 8 != 4 || castling.rook_files()[side] != [0, 7])
+    })
+}
+
+/// A game read from PGN along with its tag pairs. PGN carries no visit
+/// counts, so no move has a visit distribution.
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub game: MontyFormat,
+    /// Moves without a recognised evaluation comment, scored as 0.5.
+    pub unscored: usize,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Reads games from PGN text. Variations and NAGs are skipped, and scores
+/// are taken from eval comments such as `{+0.35/12}` (relative to the side
+/// that moved) or `{[%eval 0.35]}` (relative to white). A malformed game is
+/// returned as an error and reading continues with the next one.
+pub struct PgnReader<R> {
+    reader: R,
+    line: usize,
+    pending: Option<(usize, String)>,
+}
+
+struct PgnText {
+    line: usize,
+    tags: Vec<String>,
+    movetext: String,
+}
+
+enum Token<'a> {
+    Comment(&'a str),
+    Word(&'a str),
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: 0,
+            pending: None,
+        }
+    }
+
+    fn next_line(&mut self) -> std::io::Result<Option<(usize, String)>> {
+        if let Some(pending) = self.pending.take() {
+            return Ok(Some(pending));
+        }
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        self.line += 1;
+        Ok(Some((self.line, line)))
+    }
+
+    fn read_text(&mut self) -> std::io::Result<Option<PgnText>> {
+        let mut text = PgnText {
+            line: 0,
+            tags: Vec::new(),
+            movetext: String::new(),
+        };
+
+        let mut in_comment = false;
+
+        while let Some((number, line)) = self.next_line()? {
+            let trimmed = line.trim();
+
+            if !in_comment && trimmed.starts_with('%') {
+                continue;
+            }
+
+            if !in_comment && trimmed.starts_with('[') {
+                if !text.movetext.trim().is_empty() {
+                    self.pending = Some((number, line));
+                    break;
+                }
+
+                if text.line == 0 {
+                    text.line = number;
+                }
+
+                text.tags.push(trimmed.to_string());
+                continue;
+            }
+
+            if trimmed.is_empty() && text.movetext.is_empty() {
+                continue;
+            }
+
+            if text.line == 0 {
+                text.line = number;
+            }
+
+            for ch in line.chars() {
+                match ch {
+                    '{' if !in_comment => in_comment = true,
+                    '}' if in_comment => in_comment = false,
+                    ';' if !in_comment => break,
+                    _ => {}
+                }
+            }
+
+            text.movetext += &line;
+            if !line.ends_with('\n') {
+                text.movetext.push('\n');
+            }
+        }
+
+        if text.line == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(text))
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, MontyFormatError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_text() {
+            Ok(text) => text.map(|text| parse_game(&text)),
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+}
+
+fn parse_game(text: &PgnText) -> Result<PgnGame, MontyFormatError> {
+    let invalid = |ply, reason: String| MontyFormatError::InvalidPgn {
+        line: text.line,
+        ply,
+        reason,
+    };
+
+    let mut tags = Vec::with_capacity(text.tags.len());
+    for tag in &text.tags {
+        tags.push(parse_tag(tag).ok_or_else(|| invalid(None, format!("malformed tag {tag}")))?);
+    }
+
+    let tag = |name: &str| {
+        tags.iter()
+            .find(|(tag, _): &&(String, String)| tag == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let chess960 = tag("Variant").is_some_and(|variant| {
+        let variant = variant.to_ascii_lowercase();
+        variant.contains("960") || variant.contains("fischer")
+    });
+
+    let (startpos, castling) = setup_position(tag("FEN").unwrap_or(STARTPOS), chess960)
+        .map_err(|err| invalid(None, format!("bad FEN tag, {err}")))?;
+
+    let mut game = MontyFormat::new(startpos, castling);
+    let mut pos = startpos;
+    let mut result = tag("Result").and_then(parse_result);
+    let mut unscored = 0;
+    let mut scored = true;
+
+    for token in tokenise(&text.movetext) {
+        let word = match token {
+            Token::Comment(comment) => {
+                let Some(data) = game.moves.last_mut() else {
+                    continue;
+                };
+
+                if let Some((score, white_relative)) = parse_eval(comment) {
+                    let black_moved = pos.stm() == Side::WHITE;
+                    data.score = if white_relative && black_moved {
+                        1.0 - score
+                    } else {
+                        score
+                    };
+
+                    if !scored {
+                        unscored -= 1;
+                        scored = true;
+                    }
+                }
+
+                continue;
+            }
+            Token::Word(word) => word,
+        };
+
+        if let Some(word_result) = parse_result(word) {
+            result = result.or(Some(word_result));
+            continue;
+        }
+
+        if word == "*" || word == "e.p." || word.chars().all(|ch| "!?".contains(ch)) {
+            continue;
+        }
+
+        let word = match word.find('.') {
+            Some(idx) if word[..idx].chars().all(|ch| ch.is_ascii_digit()) => {
+                word[idx..].trim_start_matches('.')
+            }
+            _ => word,
+        };
+
+        if word.is_empty() {
+            continue;
+        }
+
+        let ply = game.moves.len();
+        let mov = pos
+            .parse_san(word, &castling)
+            .map_err(|err| invalid(Some(ply), format!("cannot play {word}, {err}")))?;
+
+        game.push(SearchData::new::<Move>(mov, 0.5, None));
+        pos.make(mov, &castling);
+
+        unscored += 1;
+        scored = false;
+    }
+
+    game.result = result.ok_or_else(|| invalid(None, "game has no result".to_string()))?;
+
+    Ok(PgnGame {
+        tags,
+        game,
+        unscored,
+    })
+}
+
+fn parse_tag(tag: &str) -> Option<(String, String)> {
+    let inner = tag.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let (name, value) = inner.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        unescaped.push(if ch == '\\' { chars.next()? } else { ch });
+    }
+
+    Some((name.to_string(), unescaped))
+}
+
+fn parse_result(result: &str) -> Option<f32> {
+    match result {
+        "1-0" => Some(1.0),
+        "0-1" => Some(0.0),
+        "1/2-1/2" => Some(0.5),
+        _ => None,
+    }
+}
+
+/// Marks the game as Chess960 if `chess960` is set, even when it starts
+/// from the standard arrangement, by reading the rights back as rook files.
+fn setup_position(fen: &str, chess960: bool) -> Result<(Position, Castling), FenError> {
+    let (pos, castling) = Position::try_parse_fen(fen)?;
+
+    if !chess960 || castling.is_chess960() {
+        return Ok((pos, castling));
+    }
+
+    let options = FenOptions {
+        castling: CastlingNotation::Shredder,
+        ..FenOptions::default()
+    };
+
+    Position::try_parse_fen(&pos.to_fen(&castling, options))
+}
+
+fn tokenise(movetext: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut rest = movetext;
+
+    while let Some(ch) = rest.chars().next() {
+        let (token, len) = match ch {
+            '{' => {
+                let end = rest.find('}').unwrap_or(rest.len());
+                (Some(Token::Comment(&rest[1..end])), end + 1)
+            }
+            ';' => {
+                let end = rest.find('\n').unwrap_or(rest.len());
+                (Some(Token::Comment(&rest[1..end])), end)
+            }
+            '(' => {
+                depth += 1;
+                (None, 1)
+            }
+            ')' => {
+                depth = depth.saturating_sub(1);
+                (None, 1)
+            }
+            '$' => {
+                let end = rest[1..]
+                    .find(|ch: char| !ch.is_ascii_digit())
+                    .map_or(rest.len(), |end| end + 1);
+                (None, end)
+            }
+            ch if ch.is_whitespace() => (None, ch.len_utf8()),
+            _ => {
+                let end = rest
+                    .find(|ch: char| ch.is_whitespace() || "{};()$".contains(ch))
+                    .unwrap_or(rest.len());
+                (Some(Token::Word(&rest[..end])), end)
+            }
+        };
+
+        if depth == 0 {
+            tokens.extend(token);
+        }
+
+        rest = &rest[len.min(rest.len())..];
+    }
+
+    tokens
+}
+
+/// Returns a win probability for the evaluation in `comment` and whether it
+/// is relative to white rather than to the side that moved.
+fn parse_eval(comment: &str) -> Option<(f32, bool)> {
+    if let Some(idx) = comment.find("[%eval ") {
+        let eval = comment[idx + 7..].split([']', ' ']).next()?;
+        return eval_to_score(eval).map(|score| (score, true));
+    }
+
+    let eval = comment.split_whitespace().next()?.split('/').next()?;
+    eval_to_score(eval).map(|score| (score, false))
+}
+
+fn eval_to_score(eval: &str) -> Option<f32> {
+    let (sign, magnitude) = match eval.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, eval.strip_prefix('+').unwrap_or(eval)),
+    };
+
+    if let Some(mate) = magnitude.strip_prefix(['M', '#']) {
+        let (sign, mate) = match mate.strip_prefix('-') {
+            Some(mate) => (-sign, mate),
+            None => (sign, mate),
+        };
+
+        mate.parse::<u32>().ok()?;
+        return Some(if sign > 0.0 { 1.0 } else { 0.0 });
+    }
+
+    if !magnitude.starts_with(|ch: char| ch.is_ascii_digit()) {
+        return None;
+    }
+
+    let pawns = sign * magnitude.parse::<f32>().ok()?;
+    Some(1.0 / (1.0 + (-100.0 * pawns / 400.0).exp()))
+}
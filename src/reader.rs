@@ -0,0 +1,191 @@
+use std::io::{BufRead, Read};
+
+use crate::{
+    BlockReader, FastDeserialise, FileHeader, MontyFormat, MontyFormatError, MontyValueFormat,
+};
+
+pub trait Deserialise: FastDeserialise + Sized {
+    fn deserialise_reusing(
+        reader: &mut impl BufRead,
+        spare: Option<Self>,
+    ) -> Result<Self, MontyFormatError>;
+}
+
+impl Deserialise for MontyFormat {
+    fn deserialise_reusing(
+        reader: &mut impl BufRead,
+        spare: Option<Self>,
+    ) -> Result<Self, MontyFormatError> {
+        MontyFormat::deserialise_reusing(reader, spare.map(|game| game.moves).unwrap_or_default())
+    }
+}
+
+impl Deserialise for MontyValueFormat {
+    fn deserialise_reusing(
+        reader: &mut impl BufRead,
+        spare: Option<Self>,
+    ) -> Result<Self, MontyFormatError> {
+        MontyValueFormat::deserialise_from(reader, spare.map(|game| game.moves).unwrap_or_default())
+    }
+}
+
+pub type MontyFormatReader<R> = GameReader<R, MontyFormat>;
+pub type MontyValueFormatReader<R> = GameReader<R, MontyValueFormat>;
+
+/// Iterates over the games in a stream, yielding `None` only when the stream
+/// ends cleanly on a game boundary.
+pub struct GameReader<R, T> {
+    reader: CountingReader<BlockReader<R>>,
+    header: Option<FileHeader>,
+    spare: Option<T>,
+    game_offset: u64,
+    finished: bool,
+    frame: Option<Vec<u8>>,
+}
+
+impl<R: BufRead, T: Deserialise> GameReader<R, T> {
+    /// Reads and checks the file header before any games, decompressing the
+    /// games that follow if the header says they are compressed.
+    pub fn new(mut reader: R) -> Result<Self, MontyFormatError> {
+        let header = FileHeader::expect(&mut reader, T::KIND)?;
+
+        let mut ret = Self::with_reader(BlockReader::new(reader, header.is_compressed()));
+        ret.reader.count = FileHeader::SIZE;
+        ret.header = Some(header);
+        Ok(ret)
+    }
+
+    /// Reads a headerless file, as written before the file header existed.
+    pub fn legacy(reader: R) -> Self {
+        Self::with_reader(BlockReader::new(reader, false))
+    }
+
+    /// `legacy` if `legacy` is set, as given by a `--legacy` flag, otherwise
+    /// `new`.
+    pub fn open(reader: R, legacy: bool) -> Result<Self, MontyFormatError> {
+        if legacy {
+            Ok(Self::legacy(reader))
+        } else {
+            Self::new(reader)
+        }
+    }
+
+    fn with_reader(reader: BlockReader<R>) -> Self {
+        Self {
+            reader: CountingReader {
+                inner: reader,
+                count: 0,
+            },
+            header: None,
+            spare: None,
+            game_offset: 0,
+            finished: false,
+            frame: None,
+        }
+    }
+
+    /// Reads each game into a buffer before decoding it, so that a game
+    /// which cannot be decoded is yielded as an error without ending the
+    /// iteration, as long as its end can still be found.
+    pub(crate) fn framed(mut self) -> Self {
+        self.frame = Some(Vec::new());
+        self
+    }
+
+    /// Raw bytes of the most recently read game, so far as they were read.
+    /// Empty unless `framed`.
+    pub(crate) fn frame(&self) -> &[u8] {
+        self.frame.as_deref().unwrap_or_default()
+    }
+
+    pub fn header(&self) -> Option<FileHeader> {
+        self.header
+    }
+
+    /// Number of bytes consumed from the underlying reader so far. For a
+    /// compressed file, bytes after the header are counted decompressed.
+    pub fn offset(&self) -> u64 {
+        self.reader.count
+    }
+
+    /// Byte offset at which the most recently yielded game starts.
+    pub fn game_offset(&self) -> u64 {
+        self.game_offset
+    }
+
+    /// Hands a game back so its allocations are reused for the next one.
+    pub fn recycle(&mut self, game: T) {
+        self.spare = Some(game);
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader.inner.into_inner()
+    }
+}
+
+impl<R: BufRead, T: Deserialise> Iterator for GameReader<R, T> {
+    type Item = Result<T, MontyFormatError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        match self.reader.fill_buf() {
+            Ok([]) => {
+                self.finished = true;
+                return None;
+            }
+            Ok(_) => {}
+            Err(err) => {
+                self.finished = true;
+                return Some(Err(MontyFormatError::Io(err)));
+            }
+        }
+
+        self.game_offset = self.reader.count;
+
+        let spare = self.spare.take();
+
+        let game = match &mut self.frame {
+            None => {
+                let game = T::deserialise_reusing(&mut self.reader, spare);
+                self.finished = game.is_err();
+                game
+            }
+            Some(frame) => match T::deserialise_fast_into_buffer(&mut self.reader, frame) {
+                Ok(_) => T::deserialise_reusing(&mut &frame[..], spare),
+                Err(err) => {
+                    self.finished = true;
+                    Err(err)
+                }
+            },
+        };
+
+        Some(game.map_err(|err| err.at_offset(self.game_offset)))
+    }
+}
+
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.count += amt as u64;
+        self.inner.consume(amt);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{
+    chess::{Castling, GameState, Move, Position, Side},
+    value::win_prob_to_cp,
+    Deserialise, MontyFormat, MontyValueFormat, SearchData, SearchResult,
+};
+
+/// Walks a recorded game from its `startpos`, yielding the position before
+/// each move alongside the data stored for it, the game result and the ply.
+pub struct Replay<'a, T> {
+    pos: Position,
+    castling: &'a Castling,
+    result: f32,
+    moves: std::slice::Iter<'a, T>,
+    ply: usize,
+    best_move: fn(&T) -> Move,
+}
+
+impl<'a, T> Replay<'a, T> {
+    fn new(
+        startpos: Position,
+        castling: &'a Castling,
+        result: f32,
+        moves: &'a [T],
+        best_move: fn(&T) -> Move,
+    ) -> Self {
+        Self {
+            pos: startpos,
+            castling,
+            result,
+            moves: moves.iter(),
+            ply: 0,
+            best_move,
+        }
+    }
+
+    pub fn castling(&self) -> &'a Castling {
+        self.castling
+    }
+
+    /// The position after every move yielded so far has been made.
+    pub fn position(&self) -> Position {
+        self.pos
+    }
+}
+
+impl<'a, T> Iterator for Replay<'a, T> {
+    type Item = (Position, &'a T, f32, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let data = self.moves.next()?;
+        let pos = self.pos;
+        let ply = self.ply;
+
+        self.pos.make((self.best_move)(data), self.castling);
+        self.ply += 1;
+
+        Some((pos, data, self.result, ply))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.moves.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for Replay<'_, T> {}
+
+impl MontyFormat {
+    pub fn replay(&self) -> Replay<'_, SearchData> {
+        Replay::new(
+            self.startpos,
+            &self.castling,
+            self.result,
+            &self.moves,
+            |data| data.best_move,
+        )
+    }
+
+    /// State after every move has been played, to see how the game ended.
+    pub fn final_state(&self) -> GameState {
+        let mut state = GameState::new(self.startpos, self.castling);
+
+        for data in &self.moves {
+            state.make(data.best_move);
+        }
+
+        state
+    }
+}
+
+impl MontyValueFormat {
+    pub fn replay(&self) -> Replay<'_, SearchResult> {
+        Replay::new(
+            self.startpos,
+            &self.castling,
+            self.result,
+            &self.moves,
+            |data| data.best_move,
+        )
+    }
+
+    /// State after every move has been played, to see how the game ended.
+    pub fn final_state(&self) -> GameState {
+        let mut state = GameState::new(self.startpos, self.castling);
+
+        for data in &self.moves {
+            state.make(data.best_move);
+        }
+
+        state
+    }
+}
+
+pub trait ScoredPositions: Deserialise {
+    /// Replays the game, calling `f` with every position, the move played,
+    /// its score in centipawns relative to the side to move, and the game
+    /// result.
+    fn map_scored_positions(&self, f: &mut impl FnMut(&Position, Move, i16, f32));
+
+    /// Castling setup the positions are played under.
+    fn castling(&self) -> &Castling;
+}
+
+impl ScoredPositions for MontyFormat {
+    fn map_scored_positions(&self, f: &mut impl FnMut(&Position, Move, i16, f32)) {
+        for (pos, data, result, _) in self.replay() {
+            f(&pos, data.best_move, win_prob_to_cp(data.score), result);
+        }
+    }
+
+    fn castling(&self) -> &Castling {
+        &self.castling
+    }
+}
+
+impl ScoredPositions for MontyValueFormat {
+    fn map_scored_positions(&self, f: &mut impl FnMut(&Position, Move, i16, f32)) {
+        for (pos, data, result, _) in self.replay() {
+            let score = if pos.stm() == Side::WHITE {
+                data.score
+            } else {
+                data.score.saturating_neg()
+            };
+
+            f(&pos, data.best_move, score, result);
+        }
+    }
+
+    fn castling(&self) -> &Castling {
+        &self.castling
+    }
+}
@@ -0,0 +1,287 @@
+use std::io::{BufRead, Write};
+
+use crate::{
+    chess::{Castling, FenOptions, Move, Position, Side},
+    GameReader, MontyFormatError, MontyValueFormat, ScoredPositions, SearchResult,
+};
+
+/// Writes every position of every game in a stream as a line of text,
+/// `fen | score | result`, followed by `| move` in UCI notation if
+/// `with_moves` is set. Scores are centipawns and results are 1.0, 0.5 or
+/// 0.0, both from white's point of view. Returns the number of positions
+/// written.
+pub fn export_text<T: ScoredPositions>(
+    mut reader: GameReader<impl BufRead, T>,
+    writer: &mut impl Write,
+    with_moves: bool,
+) -> Result<u64, MontyFormatError> {
+    let mut lines = String::new();
+    let mut positions = 0;
+
+    while let Some(game) = reader.next() {
+        let game = game?;
+        let castling = game.castling();
+
+        lines.clear();
+        game.map_scored_positions(&mut |pos, mov, score, result| {
+            let score = if pos.stm() == Side::WHITE {
+                score
+            } else {
+                score.saturating_neg()
+            };
+
+            let fen = pos.to_fen(castling, FenOptions::default());
+            lines += &format!("{fen} | {score} | {result:.1}");
+
+            if with_moves {
+                lines += &format!(" | {}", mov.to_uci(castling));
+            }
+
+            lines.push('\n');
+            positions += 1;
+        });
+
+        writer.write_all(lines.as_bytes())?;
+        reader.recycle(game);
+    }
+
+    Ok(positions)
+}
+
+struct TextLine {
+    number: usize,
+    pos: Position,
+    castling: Castling,
+    score: i16,
+    result: f32,
+    mov: Option<Move>,
+}
+
+/// Reads text written by `export_text`, grouping consecutive lines into one
+/// game while each position follows from the previous one by a legal move
+/// and the result is unchanged; a blank line also ends a game. Without a
+/// move column the move of each line is inferred from the next, so the last
+/// position of every game is lost.
+pub struct TextReader<R> {
+    reader: R,
+    line: usize,
+    pending: Option<TextLine>,
+}
+
+impl<R: BufRead> TextReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: 0,
+            pending: None,
+        }
+    }
+
+    fn read_line(&mut self) -> Result<Option<TextLine>, MontyFormatError> {
+        if let Some(line) = self.pending.take() {
+            return Ok(Some(line));
+        }
+
+        let mut text = String::new();
+
+        loop {
+            text.clear();
+            if self.reader.read_line(&mut text)? == 0 {
+                return Ok(None);
+            }
+
+            self.line += 1;
+
+            if !text.trim().is_empty() {
+                break;
+            }
+        }
+
+        let invalid = |reason: &str| MontyFormatError::InvalidText {
+            line: self.line,
+            reason: reason.to_string(),
+        };
+
+        let fields: Vec<&str> = text.split('|').map(str::trim).collect();
+
+        if !(3..=4).contains(&fields.len()) {
+            return Err(invalid("expected 3 or 4 fields"));
+        }
+
+        let (pos, castling) =
+            Position::try_parse_fen(fields[0]).map_err(|err| invalid(&err.to_string()))?;
+
+        let score = fields[1]
+            .parse::<f32>()
+            .map_err(|_| invalid("malformed score"))?;
+
+        let result = match fields[2] {
+            "1-0" => 1.0,
+            "0-1" => 0.0,
+            "1/2-1/2" => 0.5,
+            result => match result.parse::<f32>() {
+                Ok(result) if [0.0, 0.5, 1.0].contains(&result) => result,
+                _ => return Err(invalid("malformed result")),
+            },
+        };
+
+        let mov = match fields.get(3) {
+            Some(uci) => Some(
+                pos.parse_uci_move(uci, &castling)
+                    .map_err(|err| invalid(&format!("cannot play {uci}, {err}")))?,
+            ),
+            None => None,
+        };
+
+        Ok(Some(TextLine {
+            number: self.line,
+            pos,
+            castling,
+            score: score.clamp(-32767.0, 32767.0) as i16,
+            result,
+            mov,
+        }))
+    }
+}
+
+impl<R: BufRead> Iterator for TextReader<R> {
+    type Item = Result<MontyValueFormat, MontyFormatError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let first = match self.read_line() {
+                Ok(first) => first?,
+                Err(err) => return Some(Err(err)),
+            };
+
+            let mut game = MontyValueFormat {
+                startpos: first.pos,
+                castling: first.castling,
+                result: first.result,
+                moves: Vec::new(),
+            };
+
+            let mut prev = first;
+
+            loop {
+                let next = match self.read_line() {
+                    Ok(next) => next,
+                    Err(err) => return Some(Err(err)),
+                };
+
+                let connecting = next.as_ref().and_then(|next| connect(&prev, next));
+
+                match (next, connecting) {
+                    (Some(next), Some(mov)) => {
+                        push(&mut game, &prev, mov);
+                        prev = next;
+                    }
+                    (next, _) => {
+                        self.pending = next;
+                        break;
+                    }
+                }
+            }
+
+            if let Some(mov) = prev.mov {
+                push(&mut game, &prev, mov);
+            }
+
+            if !game.moves.is_empty() {
+                return Some(Ok(game));
+            }
+        }
+    }
+}
+
+fn push(game: &mut MontyValueFormat, line: &TextLine, mov: Move) {
+    game.moves.push(SearchResult {
+        best_move: mov,
+        score: line.score,
+        wdl: None,
+    });
+}
+
+/// The move leading from `prev` to `next`, if they belong to the same game.
+fn connect(prev: &TextLine, next: &TextLine) -> Option<Move> {
+    if next.number != prev.number + 1
+        || next.result != prev.result
+        || next.castling.rook_files() != prev.castling.rook_files()
+    {
+        return None;
+    }
+
+    let reaches = |mov| {
+        let mut pos = prev.pos;
+        pos.make(mov, &prev.castling);
+
+        pos.bbs() == next.pos.bbs()
+            && pos.stm() == next.pos.stm()
+            && pos.rights() == next.pos.rights()
+    };
+
+    if let Some(mov) = prev.mov {
+        return reaches(mov).then_some(mov);
+    }
+
+    let mut found = None;
+    prev.pos.map_legal_moves(&prev.castling, |mov| {
+        if reaches(mov) {
+            found = Some(mov);
+        }
+    });
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chess960_castling_round_trips() {
+        let (startpos, castling) =
+            Position::try_parse_fen("rnbqnbkr/pppppppp/8/8/8/8/PPPPPPPP/RNBQNK1R w HAha - 0 1")
+                .unwrap();
+
+        let mut game = MontyValueFormat {
+            startpos,
+            castling,
+            result: 0.5,
+            moves: Vec::new(),
+        };
+
+        // both sides castle kingside, black without moving its king
+        let mut pos = startpos;
+        for uci in ["f1h1", "e7e5", "e2e4", "f8e7", "d2d3", "g8h8", "b1c3"] {
+            let mov = pos.parse_uci_move(uci, &castling).unwrap();
+            game.push(pos.stm(), mov, 0.5);
+            pos.make(mov, &castling);
+        }
+
+        let mut bytes = Vec::new();
+        game.serialise_into(&mut bytes).unwrap();
+
+        let mut text = Vec::new();
+        let positions = export_text(
+            GameReader::<_, MontyValueFormat>::legacy(&bytes[..]),
+            &mut text,
+            true,
+        )
+        .unwrap();
+        assert_eq!(positions, 7);
+
+        let games = TextReader::new(&text[..])
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(games.len(), 1);
+
+        let read = &games[0];
+        assert_eq!(read.startpos.bbs(), startpos.bbs());
+        assert_eq!(read.castling.rook_files(), castling.rook_files());
+
+        let moves: Vec<Move> = read.moves.iter().map(|data| data.best_move).collect();
+        let expected: Vec<Move> = game.moves.iter().map(|data| data.best_move).collect();
+        assert_eq!(moves, expected);
+    }
+}
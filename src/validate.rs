@@ -0,0 +1,226 @@
+use std::io::BufRead;
+
+use crate::{
+    chess::{Castling, FenOptions, GameState, Move, Piece, Position, Side},
+    format::CompressedChessBoard,
+    GameReader, MontyFormat, MontyFormatError, MontyValueFormat, ScoredPositions,
+};
+
+pub trait Validate: ScoredPositions {
+    /// Replays an already decoded game, calling `f` with the ply, position
+    /// and description of every problem found. Returns the number of
+    /// positions checked.
+    fn check_game(&self, f: &mut impl FnMut(usize, &Position, String)) -> u64;
+}
+
+impl Validate for MontyFormat {
+    fn check_game(&self, f: &mut impl FnMut(usize, &Position, String)) -> u64 {
+        let moves = self.moves.iter().map(|data| data.best_move);
+        check_moves(self.startpos, self.castling, self.result, moves, f)
+    }
+}
+
+impl Validate for MontyValueFormat {
+    fn check_game(&self, f: &mut impl FnMut(usize, &Position, String)) -> u64 {
+        let moves = self.moves.iter().map(|data| data.best_move);
+        check_moves(self.startpos, self.castling, self.result, moves, f)
+    }
+}
+
+/// Plays through a game, checking each position and that each move is legal
+/// before it is made, then that the stored result agrees with how the game
+/// ended. Stops at an illegal move, as nothing after it can be trusted.
+fn check_moves(
+    startpos: Position,
+    castling: Castling,
+    result: f32,
+    moves: impl Iterator<Item = Move>,
+    f: &mut impl FnMut(usize, &Position, String),
+) -> u64 {
+    let mut state = GameState::new(startpos, castling);
+
+    for mov in moves {
+        let pos = *state.position();
+        let ply = state.plies();
+
+        if let Err(err) = pos.validate(&castling) {
+            f(ply, &pos, err.to_string());
+        }
+
+        let mut legal = false;
+        pos.map_legal_moves(&castling, |legal_move| legal |= legal_move == mov);
+
+        if !legal {
+            f(ply, &pos, format!("illegal move {}", mov.to_uci(&castling)));
+            return ply as u64 + 1;
+        }
+
+        state.make(mov);
+    }
+
+    check_outcome(&state, result, f);
+    state.plies() as u64
+}
+
+/// Reports a game whose final position decides it one way while its stored
+/// result says another.
+fn check_outcome(state: &GameState, result: f32, f: &mut impl FnMut(usize, &Position, String)) {
+    let pos = state.position();
+
+    if let Some(outcome) = state.outcome() {
+        if outcome.result(pos.stm()) != result {
+            f(
+                state.plies(),
+                pos,
+                format!("result {result} but game ended by {outcome}"),
+            );
+        }
+    }
+}
+
+pub struct Issue {
+    pub game: u64,
+    pub offset: u64,
+    pub ply: Option<usize>,
+    pub fen: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "game {} at byte offset {}", self.game, self.offset)?;
+
+        if let Some(ply) = self.ply {
+            write!(f, ", ply {ply}")?;
+        }
+
+        write!(f, ": {} [{}]", self.message, self.fen)
+    }
+}
+
+#[derive(Default)]
+pub struct ValidationReport {
+    pub games: u64,
+    pub positions: u64,
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl std::fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{issue}")?;
+        }
+
+        write!(
+            f,
+            "{} games, {} positions, {} issues",
+            self.games,
+            self.positions,
+            self.issues.len()
+        )
+    }
+}
+
+/// Checks every game in a stream, recording each bad record rather than
+/// stopping at the first one. Only a record whose end cannot be found ends
+/// the walk early.
+pub fn validate<T: Validate>(
+    reader: GameReader<impl BufRead, T>,
+) -> Result<ValidationReport, MontyFormatError> {
+    let mut reader = reader.framed();
+
+    let mut report = ValidationReport::default();
+
+    while let Some(game) = reader.next() {
+        let (index, offset) = (report.games, reader.game_offset());
+
+        let mut issue = |ply, fen, message| {
+            report.issues.push(Issue {
+                game: index,
+                offset,
+                ply,
+                fen,
+                message,
+            })
+        };
+
+        match game {
+            Ok(game) => {
+                let castling = game.castling();
+                let positions = game.check_game(&mut |ply, pos, message| {
+                    issue(
+                        Some(ply),
+                        pos.to_fen(castling, FenOptions::default()),
+                        message,
+                    )
+                });
+
+                report.positions += positions;
+                reader.recycle(game);
+            }
+            Err(err) => {
+                let ply = err.ply();
+                let (fen, message) = match &err {
+                    MontyFormatError::IllegalMove { mov, fen, .. } => {
+                        (fen.clone(), format!("illegal move {mov}"))
+                    }
+                    MontyFormatError::VisitCountMismatch {
+                        stored, legal, fen, ..
+                    } => (
+                        fen.clone(),
+                        format!("{stored} visit counts stored for {legal} legal moves"),
+                    ),
+                    _ => (startpos_fen(reader.frame()), err.to_string()),
+                };
+
+                issue(ply, fen, message);
+            }
+        }
+
+        report.games += 1;
+    }
+
+    Ok(report)
+}
+
+fn startpos_fen(buffer: &[u8]) -> String {
+    if buffer.len() < 38 {
+        return "-".to_string();
+    }
+
+    let mut bbs = [0; 4];
+    for (i, bb) in bbs.iter_mut().enumerate() {
+        *bb = u64::from_le_bytes(buffer[8 * i..8 * i + 8].try_into().unwrap());
+    }
+
+    let compressed = CompressedChessBoard {
+        bbs,
+        stm: buffer[32],
+        enp_sq: buffer[33] & 63,
+        rights: buffer[34] & 15,
+        halfm: buffer[35],
+        fullm: u16::from_le_bytes([buffer[36], buffer[37]]),
+    };
+
+    let pos = Position::from(compressed);
+    let has_kings = [Side::WHITE, Side::BLACK]
+        .iter()
+        .all(|&side| (pos.piece(side) & pos.piece(Piece::KING)).count_ones() == 1);
+
+    // rook files are needed to write Chess960 castling rights correctly
+    let castling = match buffer.get(38..42) {
+        Some(files) if has_kings => {
+            let files = [[files[0] & 7, files[1] & 7], [files[2] & 7, files[3] & 7]];
+            Castling::from_raw(&pos, files)
+        }
+        _ => Castling::default(),
+    };
+
+    pos.to_fen(&castling, FenOptions::default())
+}
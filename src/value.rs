@@ -1,13 +1,27 @@
+use std::io::ErrorKind;
+
 use crate::{
     chess::{Castling, Move, Position},
-    format::CompressedChessBoard,
+    format::{read_game_header, read_wdl, write_game_header, write_wdl, GameFlag},
     interleave::{interleave, FastDeserialise},
-    read_into_primitive, read_primitive_into_vec,
+    read_primitive_into_vec, FormatKind, MontyFormatError, Wdl,
 };
 
 pub struct SearchResult {
     pub best_move: Move,
     pub score: i16,
+    pub wdl: Option<Wdl>,
+}
+
+impl SearchResult {
+    /// Expected score derived from the WDL payload if present, otherwise
+    /// from the centipawn score.
+    pub fn expected_score(&self) -> f32 {
+        self.wdl.map_or_else(
+            || 1.0 / (1.0 + (-f32::from(self.score) / 400.0).exp()),
+            |wdl| wdl.expected_score(),
+        )
+    }
 }
 
 pub struct MontyValueFormat {
@@ -18,43 +32,52 @@ pub struct MontyValueFormat {
 }
 
 impl MontyValueFormat {
-    pub fn push(&mut self, stm: usize, best_move: Move, mut score: f32) {
+    pub fn push(&mut self, stm: usize, best_move: Move, score: f32) {
+        self.push_with_wdl(stm, best_move, score, None);
+    }
+
+    /// As `push`, but also records the side-to-move relative `wdl`.
+    pub fn push_wdl(&mut self, stm: usize, best_move: Move, wdl: Wdl) {
+        self.push_with_wdl(stm, best_move, wdl.expected_score(), Some(wdl));
+    }
+
+    /// As `push`, recording `wdl` if given without deriving `score` from it.
+    pub(crate) fn push_with_wdl(
+        &mut self,
+        stm: usize,
+        best_move: Move,
+        mut score: f32,
+        wdl: Option<Wdl>,
+    ) {
         if stm == 1 {
             score = 1.0 - score;
         }
 
-        let score = -(400.0 * (1.0 / score - 1.0).ln()) as i16;
-
-        self.moves.push(SearchResult { best_move, score });
+        self.moves.push(SearchResult {
+            best_move,
+            score: win_prob_to_cp(score),
+            wdl: wdl.map(|wdl| if stm == 1 { wdl.flipped() } else { wdl }),
+        });
     }
 
-    pub fn serialise_into(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
-        let compressed = CompressedChessBoard::from(self.startpos);
+    pub fn serialise_into(&self, writer: &mut impl std::io::Write) -> Result<(), MontyFormatError> {
+        let flags = if self.moves.iter().any(|data| data.wdl.is_some()) {
+            GameFlag::WDL
+        } else {
+            0
+        };
 
-        for bb in compressed.bbs {
-            writer.write_all(&bb.to_le_bytes())?;
-        }
+        write_game_header(writer, &self.startpos, &self.castling, self.result, flags)?;
 
-        writer.write_all(&compressed.stm.to_le_bytes())?;
-        writer.write_all(&compressed.enp_sq.to_le_bytes())?;
-        writer.write_all(&compressed.rights.to_le_bytes())?;
-        writer.write_all(&compressed.halfm.to_le_bytes())?;
-        writer.write_all(&compressed.fullm.to_le_bytes())?;
+        for (ply, data) in self.moves.iter().enumerate() {
+            writer.write_all(&u16::from(data.best_move).to_le_bytes())?;
+            writer.write_all(&data.score.to_le_bytes())?;
 
-        for side in self.castling.rook_files() {
-            for rook in side {
-                writer.write_all(&rook.to_le_bytes())?;
+            if flags & GameFlag::WDL > 0 {
+                write_wdl(writer, data.wdl, ply)?;
             }
         }
 
-        let result = (self.result * 2.0) as u8;
-        writer.write_all(&result.to_le_bytes())?;
-
-        for SearchResult { best_move, score } in &self.moves {
-            writer.write_all(&u16::from(*best_move).to_le_bytes())?;
-            writer.write_all(&score.to_le_bytes())?;
-        }
-
         writer.write_all(&[0; 4])?;
         Ok(())
     }
@@ -62,46 +85,17 @@ impl MontyValueFormat {
     pub fn deserialise_from(
         reader: &mut impl std::io::BufRead,
         buffer: Vec<SearchResult>,
-    ) -> std::io::Result<Self> {
-        let mut bbs = [0u64; 4];
-        for bb in &mut bbs {
-            *bb = read_into_primitive!(reader, u64);
-        }
-
-        let stm = read_into_primitive!(reader, u8);
-        let enp_sq = read_into_primitive!(reader, u8);
-        let rights = read_into_primitive!(reader, u8);
-        let halfm = read_into_primitive!(reader, u8);
-        let fullm = read_into_primitive!(reader, u16);
-
-        let compressed = CompressedChessBoard {
-            bbs,
-            stm,
-            enp_sq,
-            rights,
-            halfm,
-            fullm,
-        };
-
-        let startpos = Position::from(compressed);
-
-        let mut rook_files = [[0; 2]; 2];
-        for side in &mut rook_files {
-            for rook in side {
-                *rook = read_into_primitive!(reader, u8);
-            }
-        }
-
-        let castling = Castling::from_raw(&startpos, rook_files);
-
-        let result = read_into_primitive!(reader, u8) as f32 / 2.0;
+    ) -> Result<Self, MontyFormatError> {
+        let (startpos, castling, result, flags) = read_game_header(reader, GameFlag::WDL)?;
 
         let mut moves = buffer;
         moves.clear();
 
         loop {
             let mut buf = [0; 4];
-            reader.read_exact(&mut buf)?;
+            reader
+                .read_exact(&mut buf)
+                .map_err(|err| MontyFormatError::from(err).at_ply(moves.len()))?;
 
             if buf == [0; 4] {
                 break;
@@ -110,9 +104,16 @@ impl MontyValueFormat {
             let best_move = u16::from_le_bytes([buf[0], buf[1]]);
             let score = i16::from_le_bytes([buf[2], buf[3]]);
 
+            let wdl = if flags & GameFlag::WDL > 0 {
+                Some(read_wdl(reader).map_err(|err| err.at_ply(moves.len()))?)
+            } else {
+                None
+            };
+
             moves.push(SearchResult {
                 best_move: best_move.into(),
                 score,
+                wdl,
             });
         }
 
@@ -124,24 +125,55 @@ impl MontyValueFormat {
         })
     }
 
-    pub fn interleave(input_paths: &[String], output_path: &str, seed: u64) -> std::io::Result<()> {
-        interleave::<Self>(input_paths, output_path, seed)
+    pub fn interleave(
+        input_paths: &[String],
+        output_path: &str,
+        seed: u64,
+    ) -> Result<(), MontyFormatError> {
+        interleave::<Self>(input_paths, output_path, seed, false)
+    }
+
+    /// Interleaves headerless files written before the file header existed.
+    pub fn interleave_legacy(
+        input_paths: &[String],
+        output_path: &str,
+        seed: u64,
+    ) -> Result<(), MontyFormatError> {
+        interleave::<Self>(input_paths, output_path, seed, true)
     }
 }
 
+/// Centipawn score for a win probability, as used by `MontyValueFormat`.
+pub(crate) fn win_prob_to_cp(score: f32) -> i16 {
+    -(400.0 * (1.0 / score - 1.0).ln()) as i16
+}
+
 impl FastDeserialise for MontyValueFormat {
+    const KIND: FormatKind = FormatKind::Value;
+
     fn deserialise_fast_into_buffer(
         reader: &mut impl std::io::BufRead,
         buffer: &mut Vec<u8>,
-    ) -> std::io::Result<()> {
+    ) -> Result<usize, MontyFormatError> {
         buffer.clear();
 
+        if reader.fill_buf()?.is_empty() {
+            return Err(MontyFormatError::Io(ErrorKind::UnexpectedEof.into()));
+        }
+
         let mut buf = [0u8; 43];
         reader.read_exact(&mut buf)?;
         buffer.extend_from_slice(&buf);
 
-        while read_primitive_into_vec!(reader, buffer, u32) != 0 {}
+        let mut plies = 0;
+        while read_primitive_into_vec!(reader, buffer, u32) != 0 {
+            if buf[42] & GameFlag::WDL > 0 {
+                let _ = read_primitive_into_vec!(reader, buffer, u32);
+            }
 
-        Ok(())
+            plies += 1;
+        }
+
+        Ok(plies)
     }
 }
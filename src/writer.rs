@@ -0,0 +1,127 @@
+use std::{io::Write, marker::PhantomData};
+
+use crate::{
+    compress::{self, BLOCK_SIZE},
+    FastDeserialise, FileHeader, MontyFormat, MontyFormatError, MontyValueFormat,
+};
+
+pub trait Serialise: FastDeserialise {
+    fn serialise_reusing(
+        &self,
+        writer: &mut impl Write,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), MontyFormatError>;
+}
+
+impl Serialise for MontyFormat {
+    fn serialise_reusing(
+        &self,
+        writer: &mut impl Write,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), MontyFormatError> {
+        buffer.clear();
+        self.serialise_into_buffer(buffer)?;
+        writer.write_all(buffer)?;
+        Ok(())
+    }
+}
+
+impl Serialise for MontyValueFormat {
+    fn serialise_reusing(
+        &self,
+        writer: &mut impl Write,
+        _: &mut Vec<u8>,
+    ) -> Result<(), MontyFormatError> {
+        self.serialise_into(writer)
+    }
+}
+
+pub type MontyFormatWriter<W> = GameWriter<W, MontyFormat>;
+pub type MontyValueFormatWriter<W> = GameWriter<W, MontyValueFormat>;
+
+pub struct GameWriter<W: Write, T> {
+    writer: W,
+    buffer: Vec<u8>,
+    block: Option<Vec<u8>>,
+    games: u64,
+    _marker: PhantomData<T>,
+}
+
+impl<W: Write, T: Serialise> GameWriter<W, T> {
+    /// Writes a file header for `T` before any games.
+    pub fn new(writer: W) -> Result<Self, MontyFormatError> {
+        Self::with_header(writer, FileHeader::new(T::KIND))
+    }
+
+    /// Writes a file header with `FileHeader::COMPRESSED` set, then games in
+    /// compressed blocks. `finish` must be called to write the last block.
+    pub fn compressed(writer: W) -> Result<Self, MontyFormatError> {
+        Self::with_header(writer, FileHeader::compressed(T::KIND))
+    }
+
+    pub fn with_header(writer: W, header: FileHeader) -> Result<Self, MontyFormatError> {
+        if header.kind != T::KIND {
+            return Err(MontyFormatError::FormatMismatch {
+                expected: T::KIND,
+                found: header.kind,
+            });
+        }
+
+        let mut ret = Self::legacy(writer);
+        header.write_into(&mut ret.writer)?;
+
+        if header.is_compressed() {
+            ret.block = Some(Vec::with_capacity(BLOCK_SIZE));
+        }
+
+        Ok(ret)
+    }
+
+    /// Writes a headerless file, readable by tools predating the file header.
+    pub fn legacy(writer: W) -> Self {
+        Self {
+            writer,
+            buffer: Vec::new(),
+            block: None,
+            games: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn write(&mut self, game: &T) -> Result<(), MontyFormatError> {
+        match &mut self.block {
+            Some(block) => {
+                game.serialise_reusing(block, &mut self.buffer)?;
+
+                if block.len() >= BLOCK_SIZE {
+                    self.flush_block()?;
+                }
+            }
+            None => game.serialise_reusing(&mut self.writer, &mut self.buffer)?,
+        }
+
+        self.games += 1;
+        Ok(())
+    }
+
+    fn flush_block(&mut self) -> Result<(), MontyFormatError> {
+        if let Some(block) = &mut self.block {
+            if !block.is_empty() {
+                compress::write_block(&mut self.writer, block, &mut self.buffer)?;
+                block.clear();
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn games_written(&self) -> u64 {
+        self.games
+    }
+
+    pub fn finish(mut self) -> Result<W, MontyFormatError> {
+        self.flush_block()?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}